@@ -0,0 +1,66 @@
+//
+// Errors surfaced through the rest of the api
+
+use std::error::Error;
+use std::fmt::{ Formatter, Display };
+use std::fmt::Result as DisplayResult;
+
+use crate::{ Anomaly, AnomalyKind };
+use crate::Spanned;
+
+/// Reasons a robots.txt file couldn't be built, or couldn't answer a question about a url
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub enum RobotsError {
+    /// The url is on a different host than the robots.txt file, holds the expected and found hosts
+    HostMismatch( String, String ),
+    /// The url uses a different scheme than the robots.txt file, holds the expected and found schemes
+    SchemeMismatch( String, String ),
+    /// The url uses a different port than the robots.txt file, holds the expected and found ports
+    PortMismatch( Option< u16 >, Option< u16 > ),
+    /// The url a robots.txt file was retrieved from can't be used as a base url
+    BadUrl( String ),
+    /// No response could be had for a robots.txt file at all, holds a description of the failure
+    Unreachable( String ),
+}
+
+impl Display for RobotsError {
+    fn fmt( &self, formatter: &mut Formatter ) -> DisplayResult {
+        match self {
+            RobotsError::HostMismatch( expected, found ) => {
+                write!( formatter, "Expected a url on host {} but found one on {}", expected, found )
+            }
+            RobotsError::SchemeMismatch( expected, found ) => {
+                write!( formatter, "Expected a url using {} but found one using {}", expected, found )
+            }
+            RobotsError::PortMismatch( expected, found ) => {
+                write!( formatter, "Expected a url on port {:?} but found one on port {:?}",
+                        expected, found )
+            }
+            RobotsError::BadUrl( url ) => {
+                write!( formatter, "The url {} can't be used as a base url", url )
+            }
+            RobotsError::Unreachable( reason ) => {
+                write!( formatter, "The robots.txt file couldn't be reached: {}", reason )
+            }
+        }
+    }
+}
+
+impl Error for RobotsError {}
+
+/// The first problem found in a robots.txt document read by .parse_strict( ), holds the line it was
+/// found on, its kind and the Anomaly describing it
+#[derive( Debug, Clone, PartialEq )]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: AnomalyKind,
+    pub anomaly: Spanned< Anomaly >,
+}
+
+impl Display for ParseError {
+    fn fmt( &self, formatter: &mut Formatter ) -> DisplayResult {
+        write!( formatter, "Line {}: {}", self.line, self.anomaly.value )
+    }
+}
+
+impl Error for ParseError {}
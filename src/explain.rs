@@ -0,0 +1,82 @@
+//
+// Explaining the Verdict given for a url
+
+use std::fmt::{ Formatter, Display };
+use std::fmt::Result as DisplayResult;
+
+use base_url::BaseUrl;
+
+use crate::{ RobotsError, RobotsParser, Rule, Spanned, Verdict };
+
+/// Why a robots.txt file gave the Verdict it did for a url. Meant to be read by people, most likely
+/// through its Display implementation.
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub struct Explanation {
+    /// The path and query of the url which Rules were matched against
+    pub target: String,
+    pub user_agent: String,
+    /// The names of the User-agent section the deciding Rule came from, or of every section which was
+    /// consulted when no Rule matched
+    pub agents: Vec< String >,
+    /// The Rule which decided the Verdict, None when the default allowance applied
+    pub rule: Option< Spanned< Rule > >,
+    /// Every other Rule which matched but lost to the deciding Rule on precedence
+    pub overruled: Vec< Spanned< Rule > >,
+    pub verdict: Verdict,
+}
+
+impl Display for Explanation {
+    fn fmt( &self, formatter: &mut Formatter ) -> DisplayResult {
+        write!( formatter, "{} {} for {}", self.verdict, self.target, self.user_agent )?;
+
+        match &self.rule {
+            Some( rule ) => { write!( formatter, " by \"{}\" on line {}", rule.value, rule.span )?; }
+            None => { write!( formatter, " by default as no rule matched" )?; }
+        }
+
+        if self.agents.is_empty( ) {
+            write!( formatter, ", no User-agent section applies" )?;
+        } else {
+            write!( formatter, " in User-agent: {}", self.agents.join( ", " ) )?;
+        }
+
+        for rule in &self.overruled {
+            write!( formatter, "\n    overruling \"{}\" on line {}", rule.value, rule.span )?;
+        }
+        Ok( ( ) )
+    }
+}
+
+impl RobotsParser {
+
+    /// Gives the same Verdict as .check( ) alongside the Rule and User-agent section which decided it
+    /// and any Rules which were overruled along the way
+    pub fn explain( &self, url: &BaseUrl, user_agent: &str ) -> Result< Explanation, RobotsError > {
+
+        self.check_origin( url )?;
+
+        let groups = self.get_groups( user_agent );
+        let target = Rule::match_target( url );
+        let mut matches = self.ranked_matches( &target, &groups ).into_iter( );
+
+        let ( agents, rule, verdict ) = match matches.next( ) {
+            Some( ( agent, rule ) ) => {
+                let verdict = if rule.is_allow( ) { Verdict::Allowed } else { Verdict::Disallowed };
+                ( agent.names.clone( ), Some( rule ), verdict )
+            }
+            None => {
+                let agents = groups.iter( ).flat_map( | agent |{ agent.names.iter( ).cloned( ) } );
+                ( agents.collect( ), None, Verdict::Allowed )
+            }
+        };
+
+        Ok( Explanation{
+            target: target,
+            user_agent: user_agent.to_string( ),
+            agents: agents,
+            rule: rule.cloned( ),
+            overruled: matches.map( | ( _, rule ) |{ rule.clone( ) } ).collect( ),
+            verdict: verdict,
+        } )
+    }
+}
@@ -0,0 +1,432 @@
+//
+// Finding and downloading the robots.txt document for a site
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base_url::BaseUrl;
+use base_url::TryFrom;
+use url::Url;
+
+#[cfg( feature="fetch" )]
+use std::io::Read;
+#[cfg( feature="fetch" )]
+use reqwest::{ Client, RedirectPolicy, Response };
+#[cfg( feature="async" )]
+use futures::future::{ BoxFuture, FutureExt };
+#[cfg( feature="async" )]
+use futures::stream::{ self, BoxStream, StreamExt };
+
+use crate::{ ParseOptions, RobotsError, RobotsParser };
+#[cfg( feature="fetch" )]
+use crate::DEFAULT_MAX_BYTES;
+
+mod cache;
+pub use self::cache::*;
+
+/// How long a request for a robots.txt document may take unless another timeout is given
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs( 30 );
+
+/// The number of redirects followed before a robots.txt document is taken to be unavailable, as RFC
+/// 9309 asks
+pub const MAX_REDIRECTS: usize = 5;
+
+/// Everything a RobotsFetcher hands back about a response
+#[derive( Debug, Clone )]
+pub struct Fetched {
+    pub status: u16,
+    /// The url the response was actually served from
+    pub url: BaseUrl,
+    pub headers: Vec< ( String, String ) >,
+    pub body: Vec< u8 >,
+}
+
+/// The value of the first header with the supplied name, ignoring case
+fn find_header< 'a >( headers: &'a [ ( String, String ) ], name: &str ) -> Option< &'a str > {
+    headers.iter( ).find( | ( n, _ ) |{ n.eq_ignore_ascii_case( name ) } )
+        .map( | ( _, value ) |{ value.as_str( ) } )
+}
+
+impl Fetched {
+
+    /// The value of the first header with the supplied name, ignoring case
+    pub fn header( &self, name: &str ) -> Option< &str > {
+        find_header( &self.headers, name )
+    }
+}
+
+/// Anything which can make a GET request for a url. Fetchers shouldn't follow redirects themselves, the
+/// fetch functions follow them so that they can be counted.
+pub trait RobotsFetcher {
+    /// Requests the url, reading no more than limit bytes of the body. Fails with
+    /// RobotsError::Unreachable if no response could be had at all.
+    fn fetch( &self, url: &BaseUrl, limit: usize ) -> Result< Fetched, RobotsError >;
+}
+
+/// The root of the site a url is found on, which is the host a robots.txt document answers for
+fn site_root( url: &BaseUrl ) -> BaseUrl {
+    let mut ret = url.clone( );
+    ret.strip( );
+    ret.set_path( "/" );
+    ret
+}
+
+/// Works out where a Location header points, relative to the url which was redirected
+fn resolve( base: &BaseUrl, location: &str ) -> Option< BaseUrl > {
+    let url = Url::parse( base.as_str( ) ).ok( )?.join( location ).ok( )?;
+    BaseUrl::try_from( url.as_str( ) ).ok( )
+}
+
+/// Downloads and parses the robots.txt document for the site any url is found on using reqwest, waiting
+/// no longer than DEFAULT_TIMEOUT. See .fetch_robots_with( ) for how failures are understood, a Client
+/// which can't be built is treated the same as a site which can't be reached.
+#[cfg( feature="fetch" )]
+pub fn fetch_robots( url: &BaseUrl ) -> RobotsParser {
+    let options = ParseOptions::default( );
+    match ReqwestFetcher::new( DEFAULT_TIMEOUT ) {
+        Ok( fetcher ) => fetch_robots_with( &fetcher, url, &options ),
+        Err( _e ) => RobotsParser::unreachable_with( site_root( url ), &options ),
+    }
+}
+
+/// Downloads and parses the robots.txt document for the site any url is found on with the supplied
+/// fetcher and according to the supplied ParseOptions, including their size limit. Up to MAX_REDIRECTS
+/// redirects are followed and the status of the final response is understood the same way as by
+/// RobotsParser::from_status_and_body. Following too many redirects allows everything, while a request
+/// which fails outright disallows everything. Only a little more than the size limit is downloaded, so
+/// the Span of any Truncated Anomaly ends where downloading stopped rather than at the end of the body.
+pub fn fetch_robots_with< F: RobotsFetcher >( fetcher: &F, url: &BaseUrl, options: &ParseOptions )
+                                              -> RobotsParser {
+    let host = site_root( url );
+    let mut robots_url = host.clone( );
+    robots_url.set_path( "/robots.txt" );
+
+    match fetch_following( fetcher, &robots_url, options ) {
+        Ok( fetched ) => {
+            RobotsParser::from_status_and_body_with( host, fetched.status, &fetched.body, options )
+        }
+        Err( _e ) => RobotsParser::unreachable_with( host, options ),
+    }
+}
+
+/// How many bytes of a body are read for the supplied ParseOptions
+fn read_limit( options: &ParseOptions ) -> usize {
+    //NOTE: a line may end on the byte just past the limit, so two bytes past it are read to know
+    // whether anything was cut off
+    options.get_max_bytes( ).saturating_add( 2 )
+}
+
+/// Where a response served from a url redirects to, when it's a redirect which should be followed after
+/// the supplied number of redirects already have been
+fn next_hop( status: u16, headers: &[ ( String, String ) ], served: &BaseUrl, redirects: usize )
+             -> Option< BaseUrl > {
+    match status {
+        300..=399 if redirects < MAX_REDIRECTS => {
+            find_header( headers, "Location" ).and_then( | location |{ resolve( served, location ) } )
+        }
+        _ => None,
+    }
+}
+
+/// Requests a url following up to MAX_REDIRECTS redirects. A redirect which can't be followed, or one
+/// too many, is handed back as it is and so understood as an unavailable document.
+pub fn fetch_following< F: RobotsFetcher >( fetcher: &F, url: &BaseUrl, options: &ParseOptions )
+                                            -> Result< Fetched, RobotsError > {
+    let limit = read_limit( options );
+    let mut fetched = fetcher.fetch( url, limit )?;
+    let mut redirects = 0;
+
+    while let Some( next ) = next_hop( fetched.status, &fetched.headers, &fetched.url, redirects ) {
+        fetched = fetcher.fetch( &next, limit )?;
+        redirects += 1;
+    }
+
+    Ok( fetched )
+}
+
+/***********
+ * Fetchers
+ ******/
+
+/// Fetches with a blocking reqwest Client, which is built not to follow redirects
+#[cfg( feature="fetch" )]
+pub struct ReqwestFetcher {
+    client: Client,
+}
+
+#[cfg( feature="fetch" )]
+impl ReqwestFetcher {
+
+    /// A fetcher whose requests give up after the supplied timeout
+    pub fn new( timeout: Duration ) -> Result< Self, RobotsError > {
+        let client = Client::builder( )
+            .timeout( timeout )
+            .redirect( RedirectPolicy::none( ) )
+            .build( );
+
+        match client {
+            Ok( c ) => Ok( ReqwestFetcher{ client: c } ),
+            Err( e ) => Err( RobotsError::Unreachable( e.to_string( ) ) ),
+        }
+    }
+
+    /// A fetcher using an already configured Client, which shouldn't follow redirects itself
+    pub fn with_client( client: Client ) -> Self {
+        ReqwestFetcher{ client: client }
+    }
+}
+
+/// Reads no more than limit bytes of a response's body, the url is where the response was served from
+#[cfg( feature="fetch" )]
+fn read_response( mut response: Response, url: BaseUrl, limit: usize )
+                  -> Result< Fetched, RobotsError > {
+    let headers = response.headers( ).iter( ).filter_map( | ( name, value ) |{
+        value.to_str( ).ok( ).map( | v |{ ( name.as_str( ).to_string( ), v.to_string( ) ) } )
+    } ).collect( );
+
+    let mut body = Vec::new( );
+    if let Err( e ) = response.by_ref( ).take( limit as u64 ).read_to_end( &mut body ) {
+        return Err( RobotsError::Unreachable( e.to_string( ) ) );
+    }
+
+    Ok( Fetched{
+        status: response.status( ).as_u16( ),
+        url: url,
+        headers: headers,
+        body: body,
+    } )
+}
+
+#[cfg( feature="fetch" )]
+impl RobotsFetcher for ReqwestFetcher {
+    fn fetch( &self, url: &BaseUrl, limit: usize ) -> Result< Fetched, RobotsError > {
+        match self.client.get( url.as_str( ) ).send( ) {
+            Ok( response ) => read_response( response, url.clone( ), limit ),
+            Err( e ) => Err( RobotsError::Unreachable( e.to_string( ) ) ),
+        }
+    }
+}
+
+#[cfg( feature="fetch" )]
+impl RobotsParser {
+
+    /// Reads a robots.txt document from a response which has already been had, without following any
+    /// redirect. See .from_response_with_limit( ) for how the response is understood.
+    pub fn from_response( response: Response ) -> Result< Self, RobotsError > {
+        Self::from_response_with_limit( response, DEFAULT_MAX_BYTES )
+    }
+
+    /// Reads a robots.txt document from a response the same way as .from_response( ) but reads no more
+    /// than max_bytes of the body. The status of the response is understood the same way as by
+    /// .from_status_and_body( ) and a body which can't be read is treated as an unreachable document.
+    /// Fails only when the response came from a url which can't be a BaseUrl, prefer fetch_robots_with( )
+    /// which also follows redirects.
+    pub fn from_response_with_limit( response: Response, max_bytes: usize )
+                                     -> Result< Self, RobotsError > {
+        let url = match BaseUrl::try_from( response.url( ).clone( ) ) {
+            Ok( u ) => u,
+            Err( _e ) => { return Err( RobotsError::BadUrl( response.url( ).to_string( ) ) ); }
+        };
+        let host = site_root( &url );
+        let options = ParseOptions::default( ).max_bytes( max_bytes );
+
+        match read_response( response, url, read_limit( &options ) ) {
+            Ok( fetched ) => {
+                Ok( Self::from_status_and_body_with( host, fetched.status, &fetched.body, &options ) )
+            }
+            Err( _e ) => Ok( Self::unreachable_with( host, &options ) ),
+        }
+    }
+}
+
+/// The status, headers and body a MemoryFetcher serves for a url
+type MemoryResponse = ( u16, Vec< ( String, String ) >, Vec< u8 > );
+
+/// Serves canned responses from memory, for testing. Urls without a response are unreachable and every
+/// url requested is remembered.
+#[derive( Debug, Default )]
+pub struct MemoryFetcher {
+    responses: Mutex< HashMap< String, MemoryResponse > >,
+    requested: Mutex< Vec< String > >,
+}
+
+impl MemoryFetcher {
+
+    pub fn new( ) -> Self {
+        Self::default( )
+    }
+
+    /// Serves the supplied status and body for a url, replacing anything served before
+    pub fn respond( &self, url: &str, status: u16, body: &[u8] ) -> &Self {
+        let response = ( status, Vec::new( ), body.to_vec( ) );
+        self.responses.lock( ).unwrap( ).insert( url.to_string( ), response );
+        self
+    }
+
+    /// Adds a header to the response served for a url
+    pub fn header( &self, url: &str, name: &str, value: &str ) -> &Self {
+        if let Some( response ) = self.responses.lock( ).unwrap( ).get_mut( url ) {
+            response.1.push( ( name.to_string( ), value.to_string( ) ) );
+        }
+        self
+    }
+
+    /// Redirects a url to the supplied location with a 301 status
+    pub fn redirect( &self, url: &str, location: &str ) -> &Self {
+        self.respond( url, 301, &[ ] ).header( url, "Location", location )
+    }
+
+    /// Stops serving anything for a url, making it unreachable
+    pub fn forget( &self, url: &str ) -> &Self {
+        self.responses.lock( ).unwrap( ).remove( url );
+        self
+    }
+
+    /// Every url requested so far, in order
+    pub fn requested( &self ) -> Vec< String > {
+        self.requested.lock( ).unwrap( ).clone( )
+    }
+}
+
+impl RobotsFetcher for MemoryFetcher {
+    fn fetch( &self, url: &BaseUrl, limit: usize ) -> Result< Fetched, RobotsError > {
+        self.requested.lock( ).unwrap( ).push( url.as_str( ).to_string( ) );
+
+        match self.responses.lock( ).unwrap( ).get( url.as_str( ) ) {
+            Some( ( status, headers, body ) ) => Ok( Fetched{
+                status: *status,
+                url: url.clone( ),
+                headers: headers.clone( ),
+                body: body[ ..body.len( ).min( limit ) ].to_vec( ),
+            } ),
+            None => Err( RobotsError::Unreachable( url.as_str( ).to_string( ) ) ),
+        }
+    }
+}
+
+/***********
+ * Fetching asynchronously
+ ******/
+
+/// Everything an AsyncRobotsFetcher hands back about a response, with a body which is yet to be read
+#[cfg( feature="async" )]
+pub struct StreamedFetch< 'a > {
+    pub status: u16,
+    /// The url the response was actually served from
+    pub url: BaseUrl,
+    pub headers: Vec< ( String, String ) >,
+    pub body: BoxStream< 'a, Result< Vec< u8 >, RobotsError > >,
+}
+
+#[cfg( feature="async" )]
+impl< 'a > StreamedFetch< 'a > {
+
+    /// The value of the first header with the supplied name, ignoring case
+    pub fn header( &self, name: &str ) -> Option< &str > {
+        find_header( &self.headers, name )
+    }
+}
+
+/// Anything which can make a GET request for a url without blocking. As with RobotsFetcher redirects
+/// shouldn't be followed, and the body is only read as far as it's needed.
+///
+/// Other than MemoryFetcher, which is meant for testing, no implementation is provided. Callers supply
+/// their own around whichever async HTTP client they already use, so that this crate doesn't pick a
+/// runtime for them.
+#[cfg( feature="async" )]
+pub trait AsyncRobotsFetcher {
+    /// Requests the url. Fails with RobotsError::Unreachable if no response could be had at all.
+    fn fetch_async< 'a >( &'a self, url: BaseUrl )
+                          -> BoxFuture< 'a, Result< StreamedFetch< 'a >, RobotsError > >;
+}
+
+/// Reads chunks of a streamed body until it ends or limit bytes have been read
+#[cfg( feature="async" )]
+async fn read_body< 'a >( mut body: BoxStream< 'a, Result< Vec< u8 >, RobotsError > >, limit: usize )
+                          -> Result< Vec< u8 >, RobotsError > {
+    let mut ret = Vec::new( );
+
+    while ret.len( ) < limit {
+        match body.next( ).await {
+            Some( chunk ) => {
+                let chunk = chunk?;
+                let take = chunk.len( ).min( limit - ret.len( ) );
+                ret.extend_from_slice( &chunk[ ..take ] );
+            }
+            None => { break; }
+        }
+    }
+
+    Ok( ret )
+}
+
+/// Downloads and parses the robots.txt document for the site any url is found on without blocking, in
+/// the same way as fetch_robots_with( )
+#[cfg( feature="async" )]
+pub async fn fetch_robots_async< F: AsyncRobotsFetcher >( fetcher: &F, url: &BaseUrl,
+                                                          options: &ParseOptions ) -> RobotsParser {
+    let host = site_root( url );
+    let mut robots_url = host.clone( );
+    robots_url.set_path( "/robots.txt" );
+
+    match fetch_following_async( fetcher, &robots_url, options ).await {
+        Ok( fetched ) => {
+            RobotsParser::from_status_and_body_with( host, fetched.status, &fetched.body, options )
+        }
+        Err( _e ) => RobotsParser::unreachable_with( host, options ),
+    }
+}
+
+/// Requests a url without blocking, following redirects and reading the body in the same way as
+/// fetch_following( )
+#[cfg( feature="async" )]
+pub async fn fetch_following_async< F: AsyncRobotsFetcher >( fetcher: &F, url: &BaseUrl,
+                                                             options: &ParseOptions )
+                                                             -> Result< Fetched, RobotsError > {
+    let mut streamed = fetcher.fetch_async( url.clone( ) ).await?;
+    let mut redirects = 0;
+
+    //NOTE: the bodies of redirects are never read
+    while let Some( next ) = next_hop( streamed.status, &streamed.headers, &streamed.url, redirects ) {
+        streamed = fetcher.fetch_async( next ).await?;
+        redirects += 1;
+    }
+
+    let StreamedFetch{ status, url: served, headers, body } = streamed;
+    Ok( Fetched{
+        status: status,
+        url: served,
+        headers: headers,
+        body: read_body( body, read_limit( options ) ).await?,
+    } )
+}
+
+/// The size of the chunks a MemoryFetcher streams bodies in
+#[cfg( feature="async" )]
+const MEMORY_CHUNK: usize = 512;
+
+#[cfg( feature="async" )]
+impl AsyncRobotsFetcher for MemoryFetcher {
+    fn fetch_async< 'a >( &'a self, url: BaseUrl )
+                          -> BoxFuture< 'a, Result< StreamedFetch< 'a >, RobotsError > > {
+        async move {
+            self.requested.lock( ).unwrap( ).push( url.as_str( ).to_string( ) );
+
+            let response = self.responses.lock( ).unwrap( ).get( url.as_str( ) ).cloned( );
+            match response {
+                Some( ( status, headers, body ) ) => {
+                    let chunks: Vec< Result< Vec< u8 >, RobotsError > > = body.chunks( MEMORY_CHUNK )
+                        .map( | chunk |{ Ok( chunk.to_vec( ) ) } ).collect( );
+                    Ok( StreamedFetch{
+                        status: status,
+                        url: url,
+                        headers: headers,
+                        body: stream::iter( chunks ).boxed( ),
+                    } )
+                }
+                None => Err( RobotsError::Unreachable( url.as_str( ).to_string( ) ) ),
+            }
+        }.boxed( )
+    }
+}
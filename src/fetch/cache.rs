@@ -0,0 +1,402 @@
+//
+// Keeping the robots.txt documents of many sites around for as long as they may be used
+
+use std::collections::HashMap;
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant, SystemTime, UNIX_EPOCH };
+
+use base_url::BaseUrl;
+
+use crate::{ ParseOptions, RobotsParser };
+use super::{ Fetched, RobotsFetcher, fetch_following, site_root };
+
+/// How long a robots.txt document is used before it's downloaded again unless its response says
+/// otherwise, as RFC 9309 asks
+pub const DEFAULT_EXPIRY: Duration = Duration::from_secs( 24 * 60 * 60 );
+
+/// How long a document which couldn't be refreshed is used before trying again
+pub const DEFAULT_RETRY: Duration = Duration::from_secs( 5 * 60 );
+
+/// How many sites a RobotsCache remembers unless another capacity is given
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// The longest a document is ever used before it's downloaded again, even when a longer expiry is given
+/// to a RobotsCache
+pub const MAX_EXPIRY: Duration = Duration::from_secs( 365 * 24 * 60 * 60 );
+
+const MONTHS: [ &str; 12 ] = [ "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+                               "Jul", "Aug", "Sep", "Oct", "Nov", "Dec" ];
+
+/// The scheme, host and port a robots.txt document answers for
+type Origin = ( String, String, Option< u16 > );
+
+fn origin( url: &BaseUrl ) -> Origin {
+    ( url.scheme( ).to_ascii_lowercase( ),
+      url.host_str( ).unwrap_or( "" ).to_ascii_lowercase( ),
+      url.port_or_known_default( ) )
+}
+
+struct Entry {
+    parser: Arc< RobotsParser >,
+    expires: Instant,
+    refreshing: bool,
+    /// When the entry was last looked up, counted in lookups
+    used: u64,
+}
+
+struct Entries {
+    map: HashMap< Origin, Entry >,
+    clock: u64,
+}
+
+/// Clears the refreshing flag of an entry once a refresh is over, however it ended, so that a panic
+/// while downloading doesn't leave the old copy in use for good
+struct Refresh< 'a > {
+    entries: &'a Mutex< Entries >,
+    key: Origin,
+}
+
+impl< 'a > Drop for Refresh< 'a > {
+    fn drop( &mut self ) {
+        //NOTE: a lock poisoned by the panic being unwound still holds usable entries
+        let mut entries = match self.entries.lock( ) {
+            Ok( entries ) => entries,
+            Err( poisoned ) => poisoned.into_inner( ),
+        };
+        if let Some( entry ) = entries.map.get_mut( &self.key ) {
+            entry.refreshing = false;
+        }
+    }
+}
+
+/// When a document used for the supplied lifetime expires, no later than MAX_EXPIRY from now
+fn expires_after( lifetime: Duration ) -> Instant {
+    let now = Instant::now( );
+    now.checked_add( lifetime.min( MAX_EXPIRY ) ).unwrap_or( now )
+}
+
+impl Entries {
+
+    fn tick( &mut self ) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Forgets the least recently used entries until no more than capacity are left
+    fn evict( &mut self, capacity: usize ) {
+        while self.map.len( ) > capacity {
+            let oldest = self.map.iter( ).min_by_key( | ( _, entry ) |{ entry.used } )
+                .map( | ( origin, _ ) |{ origin.clone( ) } );
+            match oldest {
+                Some( origin ) => { self.map.remove( &origin ); }
+                None => { break; }
+            }
+        }
+    }
+}
+
+/// The parsed robots.txt documents of many sites, each downloaded with a RobotsFetcher the first time
+/// a url on that site is looked up and again once it expires. Documents are kept for DEFAULT_EXPIRY
+/// unless the Cache-Control max-age or Expires headers of their response ask for less. When a
+/// document can't be refreshed because the server answers with a 5xx status, or can't be reached at
+/// all, the last good copy is used until the next retry. Lookups made while another thread is
+/// refreshing a document are answered with the old copy rather than waiting. There's no copy to answer
+/// with for a site which isn't remembered yet, so concurrent first lookups for the same site each
+/// download its document and the last to finish is kept. Once more than capacity sites are remembered
+/// the least recently used is forgotten. No document is used for longer than MAX_EXPIRY.
+pub struct RobotsCache< F: RobotsFetcher > {
+    fetcher: F,
+    options: ParseOptions,
+    capacity: usize,
+    expiry: Duration,
+    retry: Duration,
+    entries: Mutex< Entries >,
+}
+
+impl< F: RobotsFetcher > RobotsCache< F > {
+
+    pub fn new( fetcher: F ) -> Self {
+        RobotsCache{
+            fetcher: fetcher,
+            options: ParseOptions::default( ),
+            capacity: DEFAULT_CAPACITY,
+            expiry: DEFAULT_EXPIRY,
+            retry: DEFAULT_RETRY,
+            entries: Mutex::new( Entries{ map: HashMap::new( ), clock: 0 } ),
+        }
+    }
+
+    /***********
+     * Builder
+     ******/
+
+    /// The ParseOptions documents are downloaded and parsed with
+    pub fn options( mut self, options: ParseOptions ) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// How many sites are remembered before the least recently used is forgotten
+    pub fn capacity( mut self, capacity: usize ) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// How long a document is used when its response doesn't say
+    pub fn expiry( mut self, expiry: Duration ) -> Self {
+        self.expiry = expiry;
+        self
+    }
+
+    /// How long to wait before trying again when a document couldn't be downloaded
+    pub fn retry( mut self, retry: Duration ) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /***********
+     * Getters
+     ******/
+
+    pub fn fetcher( &self ) -> &F {
+        &self.fetcher
+    }
+
+    /// The number of sites currently remembered
+    pub fn len( &self ) -> usize {
+        self.entries.lock( ).unwrap( ).map.len( )
+    }
+
+    pub fn is_empty( &self ) -> bool {
+        self.len( ) == 0
+    }
+
+    /// The parser remembered for the site a url is found on, expired or not, without downloading anything
+    pub fn cached( &self, url: &BaseUrl ) -> Option< Arc< RobotsParser > > {
+        self.entries.lock( ).unwrap( ).map.get( &origin( url ) ).map( | entry |{ entry.parser.clone( ) } )
+    }
+
+    /// Forgets the parser for the site a url is found on
+    pub fn remove( &self, url: &BaseUrl ) -> Option< Arc< RobotsParser > > {
+        self.entries.lock( ).unwrap( ).map.remove( &origin( url ) ).map( | entry |{ entry.parser } )
+    }
+
+    pub fn clear( &self ) {
+        self.entries.lock( ).unwrap( ).map.clear( );
+    }
+
+    /***********
+     * Lookups
+     ******/
+
+    /// The parser for the site any url is found on, downloading its robots.txt document if it isn't
+    /// remembered or has expired
+    pub fn get( &self, url: &BaseUrl ) -> Arc< RobotsParser > {
+        let key = origin( url );
+
+        let stale = {
+            let mut entries = self.entries.lock( ).unwrap( );
+            let used = entries.tick( );
+            match entries.map.get_mut( &key ) {
+                Some( entry ) => {
+                    entry.used = used;
+                    if entry.refreshing || Instant::now( ) < entry.expires {
+                        return entry.parser.clone( );
+                    }
+                    entry.refreshing = true;
+                    Some( entry.parser.clone( ) )
+                }
+                None => None,
+            }
+        };
+
+        let _refresh = stale.as_ref( ).map( | _ |{
+            Refresh{ entries: &self.entries, key: key.clone( ) }
+        } );
+
+        let host = site_root( url );
+        let mut robots_url = host.clone( );
+        robots_url.set_path( "/robots.txt" );
+
+        let fetched = fetch_following( &self.fetcher, &robots_url, &self.options );
+        let failed = match fetched {
+            Ok( ref fetched ) => fetched.status >= 500,
+            Err( _ ) => true,
+        };
+
+        let ( parser, lifetime ) = match stale {
+            Some( stale ) if failed => ( stale, self.retry ),
+            _ => match fetched {
+                Ok( fetched ) => {
+                    let lifetime = if failed { self.retry } else { self.lifetime( &fetched ) };
+                    let parser = RobotsParser::from_status_and_body_with( host, fetched.status, &fetched.body,
+                                                                          &self.options );
+                    ( Arc::new( parser ), lifetime )
+                }
+                Err( _e ) => {
+                    ( Arc::new( RobotsParser::unreachable_with( host, &self.options ) ), self.retry )
+                }
+            },
+        };
+
+        //NOTE: the lock is let go of before _refresh is dropped and takes it again
+        {
+            let mut entries = self.entries.lock( ).unwrap( );
+            let used = entries.tick( );
+            entries.map.insert( key, Entry{
+                parser: parser.clone( ),
+                expires: expires_after( lifetime ),
+                refreshing: false,
+                used: used,
+            } );
+            entries.evict( self.capacity );
+        }
+
+        parser
+    }
+
+    /// Checks whether an agent may visit a url, looking up the parser for its site with .get( )
+    pub fn is_allowed( &self, url: &BaseUrl, user_agent: &str ) -> bool {
+        self.get( url ).is_allowed( url, user_agent )
+    }
+
+    /// How long a downloaded document may be used for. Cache-Control max-age is preferred to Expires,
+    /// either is cut short at DEFAULT_EXPIRY.
+    fn lifetime( &self, fetched: &Fetched ) -> Duration {
+        let stated = match fetched.header( "Cache-Control" ).and_then( max_age ) {
+            Some( max_age ) => max_age,
+            None => match fetched.header( "Expires" ) {
+                Some( expires ) => match parse_http_date( expires ) {
+                    Some( expires ) => expires.duration_since( SystemTime::now( ) )
+                        .unwrap_or( Duration::from_secs( 0 ) ),
+                    //NOTE: HTTP asks that an Expires header which can't be read is taken as already
+                    // expired
+                    None => Duration::from_secs( 0 ),
+                },
+                None => { return self.expiry; }
+            },
+        };
+
+        //NOTE: RFC 9309 asks that a cached document isn't used for more than 24 hours, whatever its
+        // response says
+        stated.min( DEFAULT_EXPIRY )
+    }
+}
+
+/// The max-age directive of a Cache-Control header
+fn max_age( cache_control: &str ) -> Option< Duration > {
+    cache_control.split( ',' ).filter_map( | directive |{
+        let mut parts = directive.splitn( 2, '=' );
+        let name = parts.next( )?.trim( );
+        let value = parts.next( )?.trim( ).trim_matches( '"' );
+        if !name.eq_ignore_ascii_case( "max-age" ) {
+            return None;
+        }
+        match value.parse::< u64 >( ) {
+            Ok( seconds ) => Some( Duration::from_secs( seconds ) ),
+            //NOTE: HTTP asks that an age too large to be held is taken as the largest which can be
+            Err( _e ) if !value.is_empty( ) && value.bytes( ).all( | b |{ b.is_ascii_digit( ) } ) => {
+                Some( Duration::from_secs( u64::MAX ) )
+            }
+            Err( _e ) => None,
+        }
+    } ).next( )
+}
+
+/// Reads a date in the format HTTP sends, ie. "Sun, 06 Nov 1994 08:49:37 GMT"
+fn parse_http_date( date: &str ) -> Option< SystemTime > {
+    let words: Vec< &str > = date.split_whitespace( ).collect( );
+    if words.len( ) != 6 || words[ 5 ] != "GMT" {
+        return None;
+    }
+
+    let day: u64 = words[ 1 ].parse( ).ok( )?;
+    let month = MONTHS.iter( ).position( | month |{ month.eq_ignore_ascii_case( words[ 2 ] ) } )? as u64 + 1;
+    let year: u64 = words[ 3 ].parse( ).ok( )?;
+    let time: Vec< u64 > = words[ 4 ].split( ':' ).map( | n |{ n.parse( ).ok( ) } )
+        .collect::< Option< _ > >( )?;
+
+    if time.len( ) != 3 || year < 1970 || day == 0 || day > 31 || time[ 0 ] > 23 || time[ 1 ] > 59
+        || time[ 2 ] > 60 {
+        return None;
+    }
+
+    //NOTE: a year too far off to be counted in seconds is as unreadable as any other bad date
+    let seconds = days_since_epoch( year, month, day )?.checked_mul( 24 * 60 * 60 )?
+        .checked_add( time[ 0 ] * 60 * 60 + time[ 1 ] * 60 + time[ 2 ] )?;
+    UNIX_EPOCH.checked_add( Duration::from_secs( seconds ) )
+}
+
+/// The number of days between the first of January 1970 and a date no earlier than it, None when
+/// there are too many to count
+fn days_since_epoch( year: u64, month: u64, day: u64 ) -> Option< u64 > {
+    //NOTE: counts from March so that leap days fall at the end of each year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = ( 153 * ( ( month + 9 ) % 12 ) + 2 ) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era.checked_mul( 146097 )?.checked_add( day_of_era )?.checked_sub( 719468 )
+}
+
+#[cfg( test )]
+mod tests {
+    use super::*;
+
+    use base_url::TryFrom;
+
+    use crate::MemoryFetcher;
+
+    #[test]
+    fn http_dates( ) {
+        assert_eq!( parse_http_date( "Thu, 01 Jan 1970 00:00:00 GMT" ), Some( UNIX_EPOCH ) );
+        assert_eq!( parse_http_date( "Sun, 06 Nov 1994 08:49:37 GMT" ),
+                    Some( UNIX_EPOCH + Duration::from_secs( 784111777 ) ) );
+        assert_eq!( parse_http_date( "Tue, 29 Feb 2000 12:00:00 GMT" ),
+                    Some( UNIX_EPOCH + Duration::from_secs( 951825600 ) ) );
+        assert_eq!( parse_http_date( "0" ), None );
+        assert_eq!( parse_http_date( "Sunday, 06-Nov-94 08:49:37 GMT" ), None );
+        assert_eq!( parse_http_date( "Fri, 01 Jan 18446744073709551615 00:00:00 GMT" ), None );
+        assert_eq!( parse_http_date( "Fri, 01 Jan 600000000000 00:00:00 GMT" ), None );
+    }
+
+    #[test]
+    fn max_ages( ) {
+        assert_eq!( max_age( "max-age=3600" ), Some( Duration::from_secs( 3600 ) ) );
+        assert_eq!( max_age( "public, Max-Age=\"60\", must-revalidate" ), Some( Duration::from_secs( 60 ) ) );
+        assert_eq!( max_age( "no-cache" ), None );
+        assert_eq!( max_age( "max-age=soon" ), None );
+        assert_eq!( max_age( "max-age=99999999999999999999999" ),
+                    Some( Duration::from_secs( u64::MAX ) ) );
+        assert_eq!( max_age( "max-age=-1" ), None );
+    }
+
+    #[test]
+    fn lifetimes( ) {
+        let cache = RobotsCache::new( MemoryFetcher::new( ) ).expiry( Duration::from_secs( 60 ) );
+        let fetched = | headers: &[ ( &str, &str ) ] |{
+            Fetched{
+                status: 200,
+                url: BaseUrl::try_from( "https://example.web/robots.txt" ).ok( ).unwrap( ),
+                headers: headers.iter( ).map( | ( n, v ) |{ ( n.to_string( ), v.to_string( ) ) } ).collect( ),
+                body: Vec::new( ),
+            }
+        };
+
+        assert_eq!( cache.lifetime( &fetched( &[ ] ) ), Duration::from_secs( 60 ) );
+        assert_eq!( cache.lifetime( &fetched( &[ ( "Cache-Control", "max-age=3600" ) ] ) ),
+                    Duration::from_secs( 3600 ) );
+        assert_eq!( cache.lifetime( &fetched( &[ ( "Cache-Control", "max-age=31536000" ) ] ) ),
+                    DEFAULT_EXPIRY );
+        assert_eq!( cache.lifetime( &fetched( &[ ( "Expires", "Fri, 01 Jan 2100 00:00:00 GMT" ) ] ) ),
+                    DEFAULT_EXPIRY );
+    }
+
+    #[test]
+    fn expiries( ) {
+        let now = Instant::now( );
+        let far = expires_after( Duration::from_secs( u64::MAX ) );
+        assert!( far > now );
+        assert!( far <= Instant::now( ) + MAX_EXPIRY );
+    }
+}
@@ -3,25 +3,39 @@
 
 extern crate base_url;
 extern crate multi_order;
+extern crate url;
 
 #[cfg( feature="fetch" )]
 extern crate reqwest;
+#[cfg( feature="async" )]
+extern crate futures;
 
 use std::convert::*;
 
 use std::fmt::{ Formatter, Display };
 use std::fmt::Result as DisplayResult;
 
+use std::time::Duration;
+
 use base_url::BaseUrl;
 use multi_order::Enum;
 use multi_order::interior::sectioned_queue::*;
 
-#[cfg( fetch ) ]
-use reqwest::{ Response };
-
 mod path_match;
 use path_match::*;
 mod parse;
+mod error;
+pub use error::*;
+mod politeness;
+pub use politeness::*;
+mod explain;
+pub use explain::*;
+mod span;
+pub use span::*;
+mod options;
+pub use options::*;
+mod fetch;
+pub use fetch::*;
 /* Still here so I can figure out how to move documentation around
 #[derive( PartialEq, Debug, Clone )]
 pub enum Anomaly {
@@ -76,7 +90,22 @@ macro_rules! anomaly_enum {
             ),+
         }
 
+        /// The kind of an Anomaly without anything it holds, one for each Anomaly variant
+        #[derive( PartialEq, Eq, Debug, Clone, Copy )]
+        pub enum AnomalyKind{
+            $(
+                $id
+            ),+
+        }
+
         impl Anomaly {
+            pub fn kind( &self ) -> AnomalyKind {
+                match self {
+                    $(
+                        Anomaly::$id ( $( MatchHelper!( $arg ) ),+ ) => AnomalyKind::$id,
+                    )+
+                }
+            }
             fn header_string( &self ) -> &str {
                 match self {
                     $(
@@ -111,16 +140,33 @@ anomaly_enum! (
     "Non-standard casing in directives:" ; "Directive {0}:{1} has odd casing",
     OrphanRule ; ( Rule ) ( rule ) ;
     "Rules found outside of User-agent sections:" ; "Orphaned rule line: {}",
+    OrphanDirective ; ( String, String ) ( directive, argument ) ;
+    "Section directives found outside of User-agent sections:" ; "Orphaned directive {0}: {1}",
     RecursedUserAgent ; ( String ) ( agent );
     "User-agents found after a rule line:" ; "User-agent {} was found nested ambiguously and ignored",
     RedundantWildcardUserAgent ; ( String ) ( agent ) ;
     "Specified User-agents in a wildcard section:" ; "User-agent {} was mentioned after a wildcard",
+    MergedUserAgent ; ( String ) ( agent ) ;
+    "User-agents split across several sections:" ; "User-agent {} was merged in to an earlier section",
+    ExtraHost ; ( String ) ( host ) ;
+    "Host directives after the first:" ; "Host {} was ignored in favour of an earlier Host",
+    ExtraCrawlDelay ; ( String ) ( delay ) ;
+    "Crawl-delays after the first in a section:" ;
+    "Crawl-delay {} was ignored in favour of an earlier Crawl-delay",
     MissSectionedDirective ; ( String, String ) ( directive, argument ) ;
     "Root directives found in a User-agent section:" ; "Directive {0}: {1} found under a User-agent",
     UnknownDirective ; ( String, String ) ( directive, argument );
     "Unimplemented or unknown directives found:" ; "Unknown directive: {0}: {1}",
     BadArgument ; ( String, String ) ( directive, argument ) ;
     "Poorly formatted arguments:" ; "The argument {1} couldn't be parsed for a {0} directive",
+    InvalidEncoding ; ( String ) ( bytes ) ;
+    "Bytes which aren't valid UTF-8:" ; "The bytes {} aren't valid UTF-8 and were replaced",
+    ByteOrderMark ; ( String ) ( bytes ) ;
+    "Byte order marks starting the document:" ; "The byte order mark {} was dropped",
+    Truncated ; ( usize ) ( bytes ) ;
+    "Documents too large to read in full:" ; "Everything after the first {} bytes was ignored",
+    Misspelling ; ( String, String ) ( line, corrected ) ;
+    "Misspelt directives:" ; "The line {0} was read as {1}",
     UnknownFormat ; ( String ) ( line ) ;
     "Poorly formatted lines:" ; "Unknown line format: {}"
 );
@@ -136,39 +182,85 @@ impl Display for Anomaly {
 pub enum Rule {
     Allow( String ),
     Disallow( String ),
-    /* TODO:
-     * Crawl-delay
-     * Request-rate
-     */
-
 }
 
 impl Rule {
 
-    fn applies( &self, url: &BaseUrl ) -> bool {
-        let url_specificity = Self::path_specificity( url.path( ) );
-        let self_specificity;
-        let url_path = url.path( ).split( '/' );
-        let self_path = match self {
-            Rule::Allow( path ) | Rule::Disallow( path ) => {
-                if path == "/" || path.is_empty( ) { return true; }
-                self_specificity = Self::path_specificity( path );
-                path.split( '/' )
-            }
+    /// The path and query of a url, which is what Rules are matched against as described in RFC 9309.
+    /// The target is normalised in the same way as the paths of Rules so that equivalent spellings match.
+    fn match_target( url: &BaseUrl ) -> String {
+        normalise_path( &match url.query( ) {
+            Some( query ) => format!( "{}?{}", url.path( ), query ),
+            None => url.path( ).to_string( ),
+        } )
+    }
+
+    fn applies( &self, target: &str ) -> bool {
+        let path = match self {
+            Rule::Allow( path ) | Rule::Disallow( path ) => path,
         };
+        //NOTE: an empty path matches nothing the same as in .match_length( ), so an empty Disallow allows
+        // everything
+        if path.is_empty( ) { return false; }
+        if path == "/" { return true; }
+
+        //NOTE: an anchored path has to account for all of the target, so it's matched as a whole and its
+        // asterisks may cross a '/' the same as in RFC 9309
+        if split_anchor( path ).1 {
+            return match_prefix( target, path );
+        }
+
+        //NOTE: a query written in the path is matched from the start of the target's query as a whole,
+        // only the path is matched one segment at a time
+        let ( target_path, target_query ) = split_query( target );
+        match split_query( path ) {
+            ( self_path, Some( pattern ) ) => {
+                target_query.map_or( false, | query |{ match_prefix( query, pattern ) } )
+                    && Self::path_applies( target_path, self_path, None )
+            }
+            ( self_path, None ) => Self::path_applies( target_path, self_path, target_query ),
+        }
+    }
+
+    /// Matches a path one segment at a time. The last segment of the target may also be matched along
+    /// with the query which follows it, so that a path like "/*sessionid" still finds it in the query.
+    fn path_applies( target: &str, path: &str, query: Option< &str > ) -> bool {
+        let url_specificity = Self::path_specificity( target );
+        let url_path: Vec< &str > = target.split( '/' ).collect( );
+        let self_specificity = Self::path_specificity( path );
+        let self_path: Vec< &str > = path.split( '/' ).collect( );
 
         if url_specificity < self_specificity {
             false
         } else {
-            for segments in url_path.zip( self_path ) {
+            let last = url_path.len( ) - 1;
+            for ( i, segments ) in url_path.iter( ).zip( self_path.iter( ) ).enumerate( ) {
                 let ( url_seg, self_seg ) = segments;
-                if !match_with_asterisk( url_seg, self_seg ) {
+                let with_query = match query {
+                    Some( query ) if i == last => {
+                        match_segment( &format!( "{}?{}", url_seg, query ), self_seg )
+                    }
+                    _ => false,
+                };
+                if !match_segment( url_seg, self_seg ) && !with_query {
                     return false;
                 }
             }
             true
         }
     }
+
+    /// The length of this Rule's path if it matches the start of the supplied target, longer matches are
+    /// more specific when rules are ordered by RulePrecedence::LongestMatch. An empty path matches
+    /// nothing, so an empty Disallow allows everything as described in RFC 9309.
+    fn match_length( &self, target: &str ) -> Option< usize > {
+        match self {
+            Rule::Allow( pattern ) | Rule::Disallow( pattern ) => {
+                if pattern.is_empty( ) { return None; }
+                if match_prefix( target, pattern ) { Some( pattern.len( ) ) } else { None }
+            }
+        }
+    }
 }
 
 impl Display for Rule {
@@ -180,24 +272,201 @@ impl Display for Rule {
     }
 }
 
+/// The answer a robots.txt file gives for a url
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum Verdict {
+    Allowed,
+    Disallowed,
+}
+
+impl Verdict {
+    pub fn is_allowed( &self ) -> bool {
+        *self == Verdict::Allowed
+    }
+}
+
+impl Display for Verdict {
+    fn fmt( &self, formatter: &mut Formatter ) -> DisplayResult {
+        match self {
+            Verdict::Allowed => { write!( formatter, "Allowed" ) }
+            Verdict::Disallowed => { write!( formatter, "Disallowed" ) }
+        }
+    }
+}
+
+/// How the Rule deciding whether a path is allowed is chosen out of all the Rules which match it
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum RulePrecedence {
+    /// The Rule whose path has the most segments wins and Disallow wins a tie, paths are matched one
+    /// segment at a time
+    PathSegments,
+    /// The Rule whose path is longest in octets wins and Allow wins a tie, paths are matched from their
+    /// start as described in RFC 9309
+    LongestMatch,
+}
+
+/// How the name given on a User-agent line is compared against the user agent asking a question
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum AgentMatching {
+    /// A section applies to any user agent which starts with one of its names, case-sensitively
+    Prefix,
+    /// The product token naming the crawler is pulled out of the user agent, so that both "OurBot/2.1"
+    /// and "Mozilla/5.0 (compatible; OurBot/2.1)" give OurBot, and a section applies when one of its
+    /// names is equal to that token, case-insensitively, as described in RFC 9309
+    ProductToken,
+}
+
+impl AgentMatching {
+
+    /// The product token naming the crawler in a user agent string, the part of its first word before
+    /// any '/' version. A string written the way browsers write theirs, with the crawler named after
+    /// "compatible;" in a comment, gives the name following "compatible;" instead. Tokens have to be made
+    /// up only of letters, digits, '-' and '_'.
+    fn product_token( user_agent: &str ) -> Option< &str > {
+        fn token( words: &str ) -> Option< &str > {
+            words.trim( ).split( | c: char |{ c == '/' || c.is_whitespace( ) } ).next( )
+        }
+
+        //NOTE: the comments of "Mozilla/5.0 (compatible; OurBot/2.1)" or "Mozilla/5.0 AppleWebKit/537.36
+        // (KHTML, like Gecko; compatible; OurBot/2.1)" are searched for the crawler's own name
+        let compatible = user_agent.split( '(' ).skip( 1 )
+            .filter_map( | comment |{ comment.split( ')' ).next( ) } )
+            .filter_map( | comment |{
+                let mut items = comment.split( ';' ).skip_while( | item |{
+                    !item.trim( ).eq_ignore_ascii_case( "compatible" )
+                } );
+                items.next( )?;
+                items.next( )
+            } ).next( );
+
+        let ret = match compatible {
+            Some( item ) => token( item )?,
+            None => token( user_agent.split( '(' ).next( )? )?,
+        };
+
+        if !ret.is_empty( ) &&
+            ret.chars( ).all( | c |{ c.is_ascii_alphanumeric( ) || c == '-' || c == '_' } ) {
+            Some( ret )
+        } else {
+            None
+        }
+    }
+
+    /// The name a User-agent line gives without any '/' version, names are written with a version now
+    /// and then and those are ignored the same as in the user agent
+    fn name_token( name: &str ) -> &str {
+        name.split( '/' ).next( ).unwrap_or( name )
+    }
+
+    fn matches( &self, name: &str, user_agent: &str ) -> bool {
+        if name == "*" {
+            return true;
+        }
+
+        match self {
+            AgentMatching::Prefix => user_agent.starts_with( name ),
+            AgentMatching::ProductToken => {
+                Self::product_token( user_agent ).map_or( false, | token |{
+                    token.eq_ignore_ascii_case( Self::name_token( name ) )
+                } )
+            }
+        }
+    }
+}
+
+/// Which of the User-agent sections applying to an agent are considered when answering questions about it
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum GroupSelection {
+    /// Every section which applies is considered, named sections first, so an agent is bound by the
+    /// wildcard section as well as its own
+    Union,
+    /// Only the sections naming the agent most specifically are considered and the wildcard section is
+    /// only used when no section names the agent, as described in RFC 9309
+    MostSpecific,
+}
+
+/// The set of behaviors used to answer questions about a parsed robots.txt file. The default keeps the
+/// Rule precedence of earlier versions of this crate but matches user agents by product token,
+/// MatchingPolicy::legacy( ) keeps all of the earlier behavior and MatchingPolicy::rfc9309( ) instead
+/// matches what RFC 9309 and the major search engines expect.
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub struct MatchingPolicy {
+    pub rule_precedence: RulePrecedence,
+    pub agent_matching: AgentMatching,
+    pub group_selection: GroupSelection,
+}
+
+impl MatchingPolicy {
+
+    pub fn legacy( ) -> Self {
+        MatchingPolicy{
+            rule_precedence: RulePrecedence::PathSegments,
+            agent_matching: AgentMatching::Prefix,
+            group_selection: GroupSelection::Union,
+        }
+    }
+
+    pub fn rfc9309( ) -> Self {
+        MatchingPolicy{
+            rule_precedence: RulePrecedence::LongestMatch,
+            agent_matching: AgentMatching::ProductToken,
+            group_selection: GroupSelection::MostSpecific,
+        }
+    }
+}
+
+impl Default for MatchingPolicy {
+    fn default( ) -> Self {
+        MatchingPolicy{
+            rule_precedence: RulePrecedence::PathSegments,
+            agent_matching: AgentMatching::ProductToken,
+            group_selection: GroupSelection::Union,
+        }
+    }
+}
+
+/// The number of bytes of a robots.txt document which are read unless another limit is given, RFC 9309
+/// asks that at least 500 KiB are read and allows anything after that to be ignored
+pub const DEFAULT_MAX_BYTES: usize = 500 * 1024;
+
+/// How the lines of a robots.txt document are gathered in to User-agent sections while parsing
+#[derive( Debug, Clone, Copy, PartialEq, Eq, Default )]
+pub enum GroupingMode {
+    /// A section runs from its first User-agent line to the next empty line, any User-agent line found
+    /// after a rule is recorded as a RecursedUserAgent Anomaly and ignored
+    #[default]
+    BlankLines,
+    /// Empty lines are ignored and a User-agent line found after a rule starts a new section, as
+    /// described in RFC 9309 and the way Google reads documents
+    Rfc9309,
+}
+
 /// A User-agent section and all names, rules and anomalies associated
 #[derive( Debug, Clone )]
-struct UserAgent {
+pub struct UserAgent {
     names: Vec< String >,
-    rules: Vec< Rule >,
-    anomalies: Vec< Anomaly >,
+    rules: Vec< Spanned< Rule > >,
+    crawl_delay: Option< Spanned< Duration > >,
+    request_rates: Vec< Spanned< RequestRate > >,
+    visit_times: Vec< Spanned< VisitTime > >,
+    anomalies: Vec< Spanned< Anomaly > >,
+    span: Span,
 }
 
 impl UserAgent {
 
-    fn new( mut agent: String ) -> Self {
+    fn new( mut agent: String, span: Span ) -> Self {
         if agent.is_empty( ) {
             agent.push_str( "*" );
         }
         UserAgent{
             names: vec!( agent.to_string( ) ),
             rules: Vec::new( ),
+            crawl_delay: None,
+            request_rates: Vec::new( ),
+            visit_times: Vec::new( ),
             anomalies: Vec::new( ),
+            span: span,
         }
     }
 
@@ -205,44 +474,97 @@ impl UserAgent {
         self.rules.is_empty( )
     }
 
-    fn add_agent( &mut self, name: String ) {
+    fn add_agent( &mut self, name: String, span: Span ) {
 
         if name == "*" || self.names.contains( &String::from( "*" ) ) {
-            self.anomalies.push( Anomaly::RedundantWildcardUserAgent( name.clone( ) ) );
+            self.add_anomaly( Anomaly::RedundantWildcardUserAgent( name.clone( ) ), span );
         }
 
         if self.is_empty( ) {
             self.names.push( name );
         } else {
-            self.anomalies.push( Anomaly::RecursedUserAgent( name ) );
+            self.add_anomaly( Anomaly::RecursedUserAgent( name ), span );
         }
     }
 
-    fn add_rule( &mut self, rule: Rule ) {
-        self.rules.push( rule );
+    fn add_rule( &mut self, rule: Rule, span: Span ) {
+        self.rules.push( Spanned::new( rule, span ) );
+    }
+
+    /// Records the first Crawl-delay seen in this section, any which follow are anomalous
+    fn set_crawl_delay( &mut self, delay: Duration, argument: &str, span: Span ) {
+        if self.crawl_delay.is_some( ) {
+            self.add_anomaly( Anomaly::ExtraCrawlDelay( argument.to_string( ) ), span );
+            return;
+        }
+        self.crawl_delay = Some( Spanned::new( delay, span ) );
     }
 
-    fn add_comment( &mut self, context: String, comment: String ) {
-        self.anomalies.push( Anomaly::Comment( comment, context ) );
+    fn add_request_rate( &mut self, rate: RequestRate, span: Span ) {
+        self.request_rates.push( Spanned::new( rate, span ) );
     }
 
-    fn add_anomaly( &mut self, anomaly: Anomaly ) {
-        self.anomalies.push( anomaly );
+    fn add_visit_time( &mut self, window: VisitTime, span: Span ) {
+        self.visit_times.push( Spanned::new( window, span ) );
     }
 
-    fn applies( &self, user_agent: &str ) -> bool {
+    fn add_comment( &mut self, context: String, comment: String, span: Span ) {
+        self.add_anomaly( Anomaly::Comment( comment, context ), span );
+    }
 
-        self.names.iter( ).any( | name |{
-            user_agent.starts_with( name ) || name == "*"
-        } )
+    fn add_anomaly( &mut self, anomaly: Anomaly, span: Span ) {
+        self.anomalies.push( Spanned::new( anomaly, span ) );
+    }
+
+    /***********
+     * Getters
+     ******/
+
+    /// The names given by every User-agent line which opened this section
+    pub fn names( &self ) -> &Vec< String > {
+        &self.names
+    }
+
+    /// The Rules of this section in the order they're considered when matching by path segments
+    pub fn rules( &self ) -> &Vec< Spanned< Rule > > {
+        &self.rules
+    }
+
+    pub fn crawl_delay( &self ) -> Option< &Spanned< Duration > > {
+        self.crawl_delay.as_ref( )
+    }
+
+    pub fn request_rates( &self ) -> &Vec< Spanned< RequestRate > > {
+        &self.request_rates
+    }
+
+    pub fn visit_times( &self ) -> &Vec< Spanned< VisitTime > > {
+        &self.visit_times
+    }
+
+    /// Any anomalies, including comments, which were observed inside of this section
+    pub fn anomalies( &self ) -> &Vec< Spanned< Anomaly > > {
+        &self.anomalies
+    }
+
+    /// The Span from this section's first User-agent line to its last directive
+    pub fn span( &self ) -> Span {
+        self.span
+    }
+
+    fn applies( &self, user_agent: &str, matching: AgentMatching ) -> bool {
+
+        self.names.iter( ).any( | name |{ matching.matches( name, user_agent ) } )
     }
 }
 /// Represents a parsed robots.txt file
 pub struct RobotsParser {
     host: BaseUrl,
-    sitemaps: Vec<BaseUrl>,
+    sitemaps: Vec< Spanned< BaseUrl > >,
+    preferred_host: Option< Spanned< BaseUrl > >,
     agents: Vec<UserAgent>,
-    anomalies: Vec< Anomaly >,
+    anomalies: Vec< Spanned< Anomaly > >,
+    options: ParseOptions,
 }
 
 impl RobotsParser {
@@ -251,38 +573,90 @@ impl RobotsParser {
      * Private methods
      ******/
 
-    fn add_comment( &mut self, context: String, comment: String ) {
-        self.anomalies.push( Anomaly::Comment( comment, context ) );
+    fn add_comment( &mut self, context: String, comment: String, span: Span ) {
+        self.add_anomaly( Anomaly::Comment( comment, context ), span );
     }
 
     fn add_agent( &mut self, agent: UserAgent ) {
         self.agents.push( agent );
     }
 
-    fn add_sitemap( &mut self, url: BaseUrl ) {
-        self.sitemaps.push( url );
+    fn add_sitemap( &mut self, url: BaseUrl, span: Span ) {
+        self.sitemaps.push( Spanned::new( url, span ) );
     }
 
-    fn add_anomaly( &mut self, anomaly: Anomaly ) {
-        self.anomalies.push( anomaly );
+    fn add_anomaly( &mut self, anomaly: Anomaly, span: Span ) {
+        self.anomalies.push( Spanned::new( anomaly, span ) );
     }
 
-    fn add_unknown( &mut self, line: String ) {
-        self.anomalies.push( Anomaly::UnknownFormat( line ) );
+    /// A parser which hasn't read anything yet
+    fn empty( host: BaseUrl, options: ParseOptions ) -> Self {
+        RobotsParser{
+            host: host,
+            sitemaps: Vec::new( ),
+            preferred_host: None,
+            agents: Vec::new( ),
+            anomalies: Vec::new( ),
+            options: options,
+        }
     }
 
-    fn get_allowances( &self, user_agent: &str ) -> Vec<Rule> {
-        let agents = self.agents.iter( ).filter(
-            | agent: &&UserAgent | { agent.applies( user_agent ) }
-        );
+    fn add_unknown( &mut self, line: String, span: Span ) {
+        self.add_anomaly( Anomaly::UnknownFormat( line ), span );
+    }
 
-       let mut ret = Vec::new( );
+    fn get_groups( &self, user_agent: &str ) -> Vec< &UserAgent > {
+        let matching = self.options.get_policy( ).agent_matching;
+        let groups = self.agents.iter( ).filter(
+            | agent: &&UserAgent | { agent.applies( user_agent, matching ) }
+        );
 
-        for agent in agents {
-            ret.append( &mut agent.rules.clone( ) );
+        match self.options.get_policy( ).group_selection {
+            GroupSelection::Union => groups.collect( ),
+            GroupSelection::MostSpecific => {
+                let groups: Vec< &UserAgent > = groups.collect( );
+                //NOTE: "OurBot/1.0" and "OurBot" name the same agent, so versions are left out
+                let best = groups.iter( ).flat_map( | agent |{ agent.names.iter( ) } )
+                    .filter( | name |{ *name != "*" && matching.matches( name, user_agent ) } )
+                    .map( | name |{ AgentMatching::name_token( name ) } )
+                    .max_by_key( | name |{ name.len( ) } );
+
+                match best {
+                    Some( best ) => groups.iter( ).cloned( ).filter( | agent |{
+                        agent.names.iter( ).any( | name |{
+                            AgentMatching::name_token( name ).eq_ignore_ascii_case( best )
+                        } )
+                    } ).collect( ),
+                    None => groups.iter( ).cloned( ).filter( | agent |{
+                        agent.names.iter( ).any( | name |{ name == "*" } )
+                    } ).collect( ),
+                }
+            }
         }
+    }
 
-        ret
+    /// Every Rule in the supplied sections which matches the target, paired with the section it came
+    /// from and ordered so that the Rule deciding the Verdict comes first
+    fn ranked_matches< 'a >( &self, target: &str, groups: &[ &'a UserAgent ] )
+                             -> Vec< ( &'a UserAgent, &'a Spanned< Rule > ) > {
+        let rules = groups.iter( ).flat_map( | agent |{
+            let agent: &'a UserAgent = *agent;
+            agent.rules.iter( ).map( move | rule |{ ( agent, rule ) } )
+        } );
+
+        match self.options.get_policy( ).rule_precedence {
+            RulePrecedence::PathSegments => {
+                rules.filter( | ( _, rule ) |{ rule.applies( target ) } ).collect( )
+            }
+            RulePrecedence::LongestMatch => {
+                let mut ret: Vec< ( usize, &UserAgent, &Spanned< Rule > ) > = rules.filter_map( | ( agent, rule ) |{
+                    rule.match_length( target ).map( | length |{ ( length, agent, rule ) } )
+                } ).collect( );
+                //NOTE: the longest match comes first, and Allow comes before Disallow on a tie
+                ret.sort_by( | a, b |{ ( b.0, b.2.is_allow( ) ).cmp( &( a.0, a.2.is_allow( ) ) ) } );
+                ret.into_iter( ).map( | ( _, agent, rule ) |{ ( agent, rule ) } ).collect( )
+            }
+        }
     }
 
     /***********
@@ -297,22 +671,64 @@ impl RobotsParser {
         return ret;
     }
 
-    #[cfg( feature="fetch" )]
-    pub fn from_response( mut response: Response ) -> Self {
-        assert!( response.status( ).is_success( ) );
+    /// Builds a parser from the outcome of requesting a robots.txt document as RFC 9309 asks:
+    ///  - 2xx: the body is parsed
+    ///  - 3xx: the redirect wasn't followed, or was followed more than five times, so the document is
+    ///         unavailable and everything is allowed
+    ///  - 4xx: the document is unavailable and everything is allowed
+    ///  - 5xx, or anything else: the document is unreachable and everything is disallowed
+    pub fn from_status_and_body( host: BaseUrl, status: u16, body: &[u8] ) -> Self {
+        Self::from_status_and_body_with( host, status, body, &ParseOptions::default( ) )
+    }
 
-        let mut host = match BaseUrl::try_from( response.url( ).clone( ) ) {
-            Ok( u ) => u,
-            Err( _e ) => panic!( ),
-        };
-        host.set_path( "/" );
+    /// Builds a parser from the outcome of requesting a robots.txt document the same way as
+    /// .from_status_and_body( ), parsing any body according to the supplied ParseOptions
+    pub fn from_status_and_body_with( host: BaseUrl, status: u16, body: &[u8], options: &ParseOptions )
+                                      -> Self {
+        match status {
+            200..=299 => Self::parse_bytes_with( host, body, options ),
+            300..=499 => Self::allow_all_with( host, options ),
+            _ => Self::unreachable_with( host, options ),
+        }
+    }
 
-        let text = match response.text( ) {
-            Ok( t ) => t,
-            Err( _e ) => panic!( ),
-        };
+    /// A parser which allows every agent to visit anything, used when a site has no robots.txt
+    pub fn allow_all( host: BaseUrl ) -> Self {
+        Self::allow_all_with( host, &ParseOptions::default( ) )
+    }
 
-        Self::parse( host, text )
+    /// A parser which allows every agent to visit anything the same way as .allow_all( ), answering
+    /// questions according to the supplied ParseOptions
+    pub fn allow_all_with( host: BaseUrl, options: &ParseOptions ) -> Self {
+        Self::empty( host, *options )
+    }
+
+    /// A parser which disallows every agent from visiting anything
+    pub fn disallow_all( host: BaseUrl ) -> Self {
+        Self::disallow_all_with( host, &ParseOptions::default( ) )
+    }
+
+    /// A parser which disallows every agent from visiting anything the same way as .disallow_all( ),
+    /// answering questions according to the supplied ParseOptions
+    pub fn disallow_all_with( host: BaseUrl, options: &ParseOptions ) -> Self {
+        let mut ret = Self::empty( host, *options );
+        //NOTE: nothing was read, so the section and its rule are given empty Spans
+        let mut agent = UserAgent::new( String::from( "*" ), Span::default( ) );
+        agent.add_rule( Rule::Disallow( String::from( "/" ) ), Span::default( ) );
+        ret.add_agent( agent );
+        ret
+    }
+
+    /// A parser for a site whose robots.txt couldn't be reached, for now everything is disallowed as RFC
+    /// 9309 asks
+    pub fn unreachable( host: BaseUrl ) -> Self {
+        Self::unreachable_with( host, &ParseOptions::default( ) )
+    }
+
+    /// A parser for a site whose robots.txt couldn't be reached the same way as .unreachable( ),
+    /// answering questions according to the supplied ParseOptions
+    pub fn unreachable_with( host: BaseUrl, options: &ParseOptions ) -> Self {
+        Self::disallow_all_with( host, options )
     }
 
     pub fn from_stringable < S: Into< String > > ( stringable: S, host: BaseUrl ) -> Self {
@@ -330,24 +746,62 @@ impl RobotsParser {
         self.host.clone( )
     }
 
+    pub fn matching_policy( &self ) -> MatchingPolicy {
+        self.options.get_policy( )
+    }
+
+    /// The GroupingMode the document was parsed with
+    pub fn grouping_mode( &self ) -> GroupingMode {
+        self.options.get_grouping( )
+    }
+
+    /// The ParseOptions the document was parsed with
+    pub fn parse_options( &self ) -> ParseOptions {
+        self.options
+    }
+
     pub fn get_sitemaps( &self ) -> Vec<BaseUrl> {
-        self.sitemaps.clone( )
+        self.sitemaps.iter( ).map( | sitemap |{ sitemap.value.clone( ) } ).collect( )
+    }
+
+    /// Retrieves the sitemaps alongside the Span of the directive each was read from
+    pub fn get_sitemaps_spanned( &self ) -> &Vec< Spanned< BaseUrl > > {
+        &self.sitemaps
+    }
+
+    /// Retrieves the mirror named by the first Host directive in the document, if any. When the
+    /// directive doesn't give a scheme the scheme of this robots.txt file's host is assumed.
+    pub fn preferred_host( &self ) -> Option< BaseUrl > {
+        self.preferred_host.as_ref( ).map( | host |{ host.value.clone( ) } )
     }
 
     /// Retreives any anomalies appearing at the top level of the robots.txt document. Any anomaly not
     /// observed inside of a User-agent section is returned by this function and may contain things
     /// like orphaned and unimplemented directives.
-    pub fn get_toplevel_anomalies( &self ) -> &Vec< Anomaly > {
+    pub fn get_toplevel_anomalies( &self ) -> Vec< &Anomaly > {
+        self.anomalies.iter( ).map( | anomaly |{ &anomaly.value } ).collect( )
+    }
+
+    /// Retrieves the same anomalies as .get_toplevel_anomalies( ), each with the Span it was observed at
+    pub fn get_toplevel_anomalies_spanned( &self ) -> &Vec< Spanned< Anomaly > > {
         &self.anomalies
     }
 
-    /// Retreives any anomalies appearing under a User-agent section as determined by the agent's
-    /// .applies( ) function. That means that agent names starting with the supplied string are
-    /// returned and also that the asterisk(*) character can be supplied to indicate all agents.
+    /// Retreives any anomalies appearing under a User-agent section which applies to the supplied user
+    /// agent, as decided by the MatchingPolicy. The asterisk(*) character can be supplied to indicate
+    /// all agents.
     pub fn get_agent_anomalies( &self, user_agent: &str ) -> Vec< &Anomaly > {
-        let agents = self.agents.iter( ).filter(
-            | agent: &&UserAgent | { agent.applies( user_agent ) }
-        );
+        self.get_agent_anomalies_spanned( user_agent ).into_iter( ).map( | anomaly |{ &anomaly.value } )
+            .collect( )
+    }
+
+    /// Retrieves the same anomalies as .get_agent_anomalies( ), each with the Span it was observed at
+    pub fn get_agent_anomalies_spanned( &self, user_agent: &str ) -> Vec< &Spanned< Anomaly > > {
+        let agents = if user_agent == "*" {
+            self.agents.iter( ).collect( )
+        } else {
+            self.get_groups( user_agent )
+        };
 
         let mut ret = Vec::new( );
 
@@ -362,28 +816,103 @@ impl RobotsParser {
     /// Note that Anomalies are not necessarily reported in the order they were found in the document
     /// and are instead sorted
     pub fn get_all_anomalies( &self ) -> Vec<&Anomaly> {
+        self.get_all_anomalies_spanned( ).into_iter( ).map( | anomaly |{ &anomaly.value } ).collect( )
+    }
+
+    /// Retrieves the same anomalies as .get_all_anomalies( ), each with the Span it was observed at
+    pub fn get_all_anomalies_spanned( &self ) -> Vec< &Spanned< Anomaly > > {
 
         let mut ret = Vec::new( );
 
         ret.extend( self.anomalies.iter( ) );
-        ret.extend( self.get_agent_anomalies( "*" ) );
+        for agent in self.groups( ) {
+            ret.extend( agent.anomalies.iter( ) );
+        }
 
         ret
     }
 
+    /// Iterates over every User-agent section in the document in the order they're considered when
+    /// answering questions about an agent, named sections before wildcards
+    pub fn groups( &self ) -> impl Iterator< Item = &UserAgent > + '_ {
+        self.agents.iter( )
+    }
+
+    /// Retrieves every Rule which applies to the supplied user agent, in the order of the sections they
+    /// came from. These are the Rules .is_allowed( ) chooses between.
+    pub fn rules_for( &self, user_agent: &str ) -> Vec< &Spanned< Rule > > {
+        self.get_groups( user_agent ).into_iter( ).flat_map( | agent |{ agent.rules.iter( ) } ).collect( )
+    }
+
+    /// Retrieves the Crawl-delay requested of the supplied user agent, if any. User-agent sections are
+    /// considered in the same order as in .is_allowed( ), so a section naming the agent takes precedence
+    /// over a wildcard section.
+    pub fn crawl_delay( &self, user_agent: &str ) -> Option< Duration > {
+        self.get_groups( user_agent ).into_iter( ).filter_map( | agent |{ agent.crawl_delay.as_ref( ) } )
+            .map( | delay |{ delay.value } ).next( )
+    }
+
+    /// Gathers the Crawl-delay, Request-rates and Visit-times which apply to the supplied user agent in
+    /// to a single answer. Each is taken from the first User-agent section which applies and specifies
+    /// it, in the same order as in .is_allowed( ).
+    pub fn politeness( &self, user_agent: &str ) -> Politeness {
+        let agents = self.get_groups( user_agent );
+
+        Politeness{
+            crawl_delay: self.crawl_delay( user_agent ),
+            request_rates: agents.iter( ).map( | agent |{ &agent.request_rates } )
+                .find( | rates |{ !rates.is_empty( ) } )
+                .map( | rates |{ rates.iter( ).map( | rate |{ rate.value } ).collect( ) } )
+                .unwrap_or_default( ),
+            visit_times: agents.iter( ).map( | agent |{ &agent.visit_times } )
+                .find( | windows |{ !windows.is_empty( ) } )
+                .map( | windows |{ windows.iter( ).map( | window |{ window.value } ).collect( ) } )
+                .unwrap_or_default( ),
+        }
+    }
+
+    /// Makes sure a url shares an origin (scheme, host and port) with this robots.txt file
+    fn check_origin( &self, url: &BaseUrl ) -> Result< ( ), RobotsError > {
+        if url.scheme( ) != self.host.scheme( ) {
+            return Err( RobotsError::SchemeMismatch( self.host.scheme( ).to_string( ),
+                                                     url.scheme( ).to_string( ) ) );
+        }
+        if url.host_str( ) != self.host.host_str( ) {
+            return Err( RobotsError::HostMismatch( self.host.host_str( ).unwrap_or( "" ).to_string( ),
+                                                   url.host_str( ).unwrap_or( "" ).to_string( ) ) );
+        }
+        if url.port_or_known_default( ) != self.host.port_or_known_default( ) {
+            return Err( RobotsError::PortMismatch( self.host.port_or_known_default( ),
+                                                   url.port_or_known_default( ) ) );
+        }
+        Ok( ( ) )
+    }
+
     /// Given a url and a user agent string determines if this robots.txt disallows browsing to that
-    /// url. This is generally understood as more of a suggestion than a rule.
-    //HACK: Can we combine the search through the UserAgents and the search for allowances in a way
-    // which is clean?
-    pub fn is_allowed( &self, url: &BaseUrl, user_agent: &str ) -> bool {
+    /// url. Urls which don't share a scheme, host and port with this robots.txt file are reported as a
+    /// RobotsError rather than answered.
+    pub fn check( &self, url: &BaseUrl, user_agent: &str ) -> Result< Verdict, RobotsError > {
 
-        assert!( url.host( ) == self.host_url( ).host( ) );
+        self.check_origin( url )?;
 
-        for rule in self.get_allowances( user_agent ) {
-            if rule.applies( &url ) {
-                return rule.is_allow( );
-            }
+        let groups = self.get_groups( user_agent );
+        let target = Rule::match_target( url );
+
+        let allowance = self.ranked_matches( &target, &groups ).first( )
+            .map( | ( _, rule ) |{ rule.is_allow( ) } ).unwrap_or( true );
+
+        Ok( if allowance { Verdict::Allowed } else { Verdict::Disallowed } )
+    }
+
+    /// Given a url and a user agent string determines if this robots.txt disallows browsing to that
+    /// url. This is generally understood as more of a suggestion than a rule.
+    ///
+    /// A url which doesn't share a scheme, host and port with this robots.txt file isn't something it
+    /// can allow, so false is returned. Use .check( ) to tell the two apart.
+    pub fn is_allowed( &self, url: &BaseUrl, user_agent: &str ) -> bool {
+        match self.check( url, user_agent ) {
+            Ok( verdict ) => verdict.is_allowed( ),
+            Err( _e ) => false,
         }
-        true
     }
 }
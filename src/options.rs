@@ -0,0 +1,141 @@
+//
+// Settings for how a robots.txt document is read and how questions about it are answered
+
+use crate::{ DEFAULT_MAX_BYTES, GroupingMode, MatchingPolicy };
+
+/// Everything which can be changed about how a robots.txt document is parsed, built up by chaining
+/// calls from either ParseOptions::lenient( ), the default used by .parse( ), or
+/// ParseOptions::verbatim( ) and handed to .parse_with( )
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub struct ParseOptions {
+    collect_comments: bool,
+    fix_casing: bool,
+    fix_misspellings: bool,
+    strip_bom: bool,
+    extensions: bool,
+    max_bytes: usize,
+    grouping: GroupingMode,
+    policy: MatchingPolicy,
+}
+
+impl ParseOptions {
+
+    /// Reads as much of a document as can be made sense of, correcting casing and misspellings and
+    /// understanding the Host, Crawl-delay, Request-rate and Visit-time extensions
+    pub fn lenient( ) -> Self {
+        ParseOptions{
+            collect_comments: true,
+            fix_casing: true,
+            fix_misspellings: true,
+            strip_bom: true,
+            extensions: true,
+            max_bytes: DEFAULT_MAX_BYTES,
+            grouping: GroupingMode::default( ),
+            policy: MatchingPolicy::default( ),
+        }
+    }
+
+    /// Reads a document only as it's written, directives with odd casing or misspelt names are left as
+    /// unknown directives rather than corrected
+    pub fn verbatim( ) -> Self {
+        ParseOptions{
+            fix_casing: false,
+            fix_misspellings: false,
+            .. Self::lenient( )
+        }
+    }
+
+    /***********
+     * Builder
+     ******/
+
+    /// Whether comments are recorded as Comment Anomalies, turning this off makes parsing cheaper
+    pub fn collect_comments( mut self, collect: bool ) -> Self {
+        self.collect_comments = collect;
+        self
+    }
+
+    /// Whether directive names with odd casing are read as the directive they name
+    pub fn fix_casing( mut self, fix: bool ) -> Self {
+        self.fix_casing = fix;
+        self
+    }
+
+    /// Whether misspelt directive names and lines missing their colon are read as the directive they name
+    pub fn fix_misspellings( mut self, fix: bool ) -> Self {
+        self.fix_misspellings = fix;
+        self
+    }
+
+    /// Whether a leading byte order mark is dropped
+    pub fn strip_bom( mut self, strip: bool ) -> Self {
+        self.strip_bom = strip;
+        self
+    }
+
+    /// Whether the Host, Crawl-delay, Request-rate and Visit-time directives, which aren't part of RFC
+    /// 9309, are understood or recorded as unknown directives
+    pub fn extensions( mut self, understood: bool ) -> Self {
+        self.extensions = understood;
+        self
+    }
+
+    /// How many bytes of a document are read before the rest is ignored
+    pub fn max_bytes( mut self, max_bytes: usize ) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn grouping( mut self, grouping: GroupingMode ) -> Self {
+        self.grouping = grouping;
+        self
+    }
+
+    /// The MatchingPolicy used to answer questions about the parsed document
+    pub fn policy( mut self, policy: MatchingPolicy ) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /***********
+     * Getters
+     ******/
+
+    pub fn collects_comments( &self ) -> bool {
+        self.collect_comments
+    }
+
+    pub fn fixes_casing( &self ) -> bool {
+        self.fix_casing
+    }
+
+    pub fn fixes_misspellings( &self ) -> bool {
+        self.fix_misspellings
+    }
+
+    pub fn strips_bom( &self ) -> bool {
+        self.strip_bom
+    }
+
+    pub fn understands_extensions( &self ) -> bool {
+        self.extensions
+    }
+
+    pub fn get_max_bytes( &self ) -> usize {
+        self.max_bytes
+    }
+
+    pub fn get_grouping( &self ) -> GroupingMode {
+        self.grouping
+    }
+
+    pub fn get_policy( &self ) -> MatchingPolicy {
+        self.policy
+    }
+}
+
+impl Default for ParseOptions {
+    fn default( ) -> Self {
+        Self::lenient( )
+    }
+}
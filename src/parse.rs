@@ -1,23 +1,31 @@
 //
-// TODO: Line numbering, HACK commments
+// TODO: HACK commments
 
 use std::cmp::Ordering;
+use std::net::Ipv6Addr;
+use std::str;
+use std::time::Duration;
 use std::usize::MAX;
 
 use base_url::BaseUrl;
 use base_url::TryFrom;
 
-use crate::Anomaly;
+use crate::{ Anomaly, AnomalyKind };
+use crate::ParseError;
+use crate::GroupingMode;
+use crate::ParseOptions;
+use crate::{ RequestRate, VisitTime };
 use crate::Rule;
 use crate::UserAgent;
 use crate::RobotsParser;
+use crate::{ Span, Spanned };
+use crate::path_match::normalise_path;
 
 impl Rule {
 
-    fn new( allowance: bool, mut path: String ) -> Rule {
-        if path.is_empty( ) {
-            path.push_str( "/" );
-        }
+    //NOTE: an empty path is kept as it's written, how it's understood depends on the RulePrecedence
+    fn new( allowance: bool, path: String ) -> Rule {
+        let path = normalise_path( &path );
         match allowance {
             true => {
                 Rule::Allow( path )
@@ -32,20 +40,9 @@ impl Rule {
      * Ordering Helpers
      ******/
     pub fn is_allow( &self ) -> bool {
-        //NOTE: if the path is empty that classically means that no paths are allowed/disallowed so we
-        // flip the allowance returned, if no paths are disallowed then everything is allowed
-        let mul = match self {
-            Rule::Allow( path ) | Rule::Disallow( path ) => {
-                if path.is_empty( ) {
-                    false
-                } else {
-                    true
-                }
-            }
-        };
         match self {
-            Rule::Allow( _ ) => { mul == true }
-            _ => { mul == false }
+            Rule::Allow( _ ) => true,
+            Rule::Disallow( _ ) => false,
         }
     }
 
@@ -62,8 +59,8 @@ impl Rule {
     }
 }
 
-/// Rules are ordered first by specificity then by their allowance. That means that the most specific
-/// disallow rule is found first and the least specific allow rule is considered last.
+/// Rules are ordered first by specificity, most path segments first, then by their allowance. That means
+/// that the most specific disallow rule is found first and the least specific allow rule is considered last.
 impl PartialOrd for Rule {
 
     fn partial_cmp( &self, rhs: &Self ) -> Option< Ordering > {
@@ -80,7 +77,7 @@ impl PartialOrd for Rule {
                     Ordering::Less
                 } )
         } else {
-            Some( left_spec.cmp( &right_spec ) )
+            Some( right_spec.cmp( &left_spec ) )
         }
     }
 }
@@ -98,6 +95,33 @@ impl UserAgent {
             if min < name.len() { min } else { name.len( ) }
         } )
     }
+
+    /// Whether two sections name the same set of agents, ignoring case and the order they're named in
+    fn same_names( &self, rhs: &Self ) -> bool {
+        let fold = | names: &Vec< String > |{
+            let mut folded: Vec< String > = names.iter( )
+                .map( | name |{ name.to_ascii_lowercase( ) } ).collect( );
+            folded.sort( );
+            folded.dedup( );
+            folded
+        };
+        fold( &self.names ) == fold( &rhs.names )
+    }
+
+    /// Takes on everything found in a later section naming the same agents, as RFC 9309 asks
+    fn merge( &mut self, later: UserAgent ) {
+        self.add_anomaly( Anomaly::MergedUserAgent( later.names.join( ", " ) ), later.span );
+
+        self.rules.extend( later.rules );
+        self.rules.sort_by( | a, b |{ a.value.cmp( &b.value ) } );
+        //NOTE: the earlier section's Crawl-delay wins, the same as the first section found when asking
+        if self.crawl_delay.is_none( ) {
+            self.crawl_delay = later.crawl_delay;
+        }
+        self.request_rates.extend( later.request_rates );
+        self.visit_times.extend( later.visit_times );
+        self.anomalies.extend( later.anomalies );
+    }
 }
 
 impl PartialEq for UserAgent {
@@ -137,12 +161,12 @@ impl Ord for UserAgent {
 
 #[allow(non_camel_case_types)]
 enum R_State { //Recursed state; useragent sections don't recurse, they add
-    Comment( UserAgent, String ),
+    Comment( UserAgent, String, Span ),
     Normal( UserAgent ),
 }
 
 enum State {
-    Comment( RobotsParser, String ), //We have a comment, but we can't see any context yet
+    Comment( RobotsParser, String, Span ), //We have a comment, but we can't see any context yet
     Agent( RobotsParser, R_State ), //We are inside of a useragent section
     Normal( RobotsParser ), //Any lines at the root level (those without a useragent association)
 }
@@ -152,13 +176,109 @@ enum DirectiveResult {
     Ok_UserAgent( String ),
     Ok_Rule( Rule ),
     Ok_Sitemap( BaseUrl ),
-    //Ok_RequestRate( u32 ),
-    //Ok_CrawlDelay( u32 ),
+    Ok_Host( String ),
+    Ok_RequestRate( RequestRate ),
+    Ok_VisitTime( VisitTime ),
+    Ok_CrawlDelay( Duration ),
     Err_BadArg(),
     Unknown(),
 }
 
-fn parse_directive( directive: &str, argument: &str ) -> DirectiveResult {
+/// Checks that a Host argument is a host name, IPv4 address or bracketed IPv6 address, optionally
+/// preceded by an http(s) scheme and followed by a port
+fn valid_host( argument: &str ) -> bool {
+    let authority = argument.trim_start_matches( "https://" ).trim_start_matches( "http://" );
+
+    //NOTE: an IPv6 address is full of colons, so its port can only be found after the closing bracket
+    if authority.starts_with( '[' ) {
+        return match authority.find( ']' ) {
+            Some( i ) => {
+                let port = &authority[ i + 1.. ];
+                authority[ 1..i ].parse::< Ipv6Addr >( ).is_ok( ) &&
+                    ( port.is_empty( ) ||
+                      ( port.starts_with( ':' ) && port[ 1.. ].parse::< u16 >( ).is_ok( ) ) )
+            }
+            None => false,
+        };
+    }
+
+    let host = match authority.rfind( ':' ) {
+        Some( i ) => {
+            if authority[ i + 1.. ].parse::< u16 >( ).is_err( ) { return false; }
+            &authority[ ..i ]
+        }
+        None => authority,
+    };
+
+    !host.is_empty( ) && host.split( '.' ).all( | label |{
+        !label.is_empty( ) && !label.starts_with( '-' ) && !label.ends_with( '-' ) &&
+            label.chars( ).all( | c |{ c.is_ascii_alphanumeric( ) || c == '-' } )
+    } )
+}
+
+/// Recognises the known directives through the misspellings and alternate spellings common in the wild,
+/// handing back the directive's proper name
+fn recognise_directive( name: &str ) -> Option< &'static str > {
+    let key: String = name.chars( ).filter( | c |{ *c != '-' && *c != '_' && !c.is_whitespace( ) } )
+        .collect::< String >( ).to_ascii_lowercase( );
+
+    match key.as_str( ) {
+        "useragent" => Some( "User-agent" ),
+        "disallow" | "dissallow" | "dissalow" | "disalow" | "diasllow" | "disallaw" => Some( "Disallow" ),
+        "allow" => Some( "Allow" ),
+        "sitemap" => Some( "Sitemap" ),
+        "host" => Some( "Host" ),
+        "crawldelay" => Some( "Crawl-delay" ),
+        "requestrate" => Some( "Request-rate" ),
+        "visittime" => Some( "Visit-time" ),
+        _ => None,
+    }
+}
+
+/// Splits a line without a colon after the name of a known directive, which may be written as two words.
+/// A name with nothing after it isn't split, as it could only be read as a directive without an argument.
+fn split_words( line: &str ) -> Option< ( &str, &str ) > {
+    let first = line.split_whitespace( ).next( )?;
+    let name = if recognise_directive( first ).is_some( ) {
+        first
+    } else {
+        let rest = line[ first.len( ).. ].trim_start( );
+        let second = rest.split_whitespace( ).next( )?;
+        let name = &line[ ..line.len( ) - rest.len( ) + second.len( ) ];
+        recognise_directive( name )?;
+        name
+    };
+
+    //NOTE: a bare "Disallow" read as "Disallow:" would turn a typo in to a rule
+    let rest = &line[ name.len( ).. ];
+    if rest.trim( ).is_empty( ) { None } else { Some( ( name, rest ) ) }
+}
+
+/// Splits a line in to a directive name and the rest of the line, which still holds any colon, along
+/// with whether the name was followed directly by a colon. A line without a colon after its name is only
+/// split when it starts with a known directive.
+fn split_directive( line: &str ) -> Option< ( &str, &str, bool ) > {
+    if let Some( i ) = line.find( ':' ) {
+        let ( name, rest ) = line.split_at( i );
+        let trimmed = name.trim_end( );
+        //NOTE: "Sitemap http://..." has a colon, just not one following the directive
+        if recognise_directive( trimmed ).is_some( ) || split_words( line ).is_none( ) {
+            return Some( ( trimmed, rest, trimmed.len( ) == name.len( ) ) );
+        }
+    }
+
+    split_words( line ).map( | ( name, rest ) |{ ( name, rest, false ) } )
+}
+
+fn parse_directive( directive: &str, argument: &str, options: &ParseOptions ) -> DirectiveResult {
+    let extension = match directive {
+        "Host" | "Crawl-delay" | "Request-rate" | "Visit-time" => true,
+        _ => false,
+    };
+    if extension && !options.understands_extensions( ) {
+        return DirectiveResult::Unknown( );
+    }
+
     match directive {
         "User-agent" => {
             DirectiveResult::Ok_UserAgent( argument.to_string( ) )
@@ -177,118 +297,210 @@ fn parse_directive( directive: &str, argument: &str ) -> DirectiveResult {
                 DirectiveResult::Err_BadArg()
             }
         }
+        "Host" => {
+            if valid_host( argument ) {
+                DirectiveResult::Ok_Host( argument.to_string( ) )
+            } else {
+                DirectiveResult::Err_BadArg()
+            }
+        }
+        "Crawl-delay" => {
+            //NOTE: fractional delays are common enough in the wild that we accept them too, anything too
+            // large for a Duration is as unusable as a negative delay
+            match argument.parse::< f64 >( ) {
+                Ok( secs ) if secs.is_finite( ) && secs >= 0.0 && secs < u64::MAX as f64 => {
+                    DirectiveResult::Ok_CrawlDelay( Duration::from_secs_f64( secs ) )
+                }
+                _ => {
+                    DirectiveResult::Err_BadArg()
+                }
+            }
+        }
+        "Request-rate" => {
+            match RequestRate::parse( argument ) {
+                Some( rate ) => DirectiveResult::Ok_RequestRate( rate ),
+                None => DirectiveResult::Err_BadArg(),
+            }
+        }
+        "Visit-time" => {
+            match VisitTime::parse( argument ) {
+                Some( window ) => DirectiveResult::Ok_VisitTime( window ),
+                None => DirectiveResult::Err_BadArg(),
+            }
+        }
         _ => {
             DirectiveResult::Unknown()
         }
     }
 }
 
+/// Where a document should be cut so that it's no longer than max_bytes, just after the last full line
+/// which fits
+fn line_boundary( bytes: &[u8], max_bytes: usize ) -> usize {
+    if bytes.len( ) <= max_bytes {
+        return bytes.len( );
+    }
+
+    //NOTE: a line which fits in max_bytes is kept even when its line ending is the byte after the limit
+    bytes[ ..=max_bytes ].iter( ).rposition( | byte |{ *byte == b'\n' } )
+        .map( | i |{ i + 1 } ).unwrap_or( 0 )
+}
+
+/// Whether an Anomaly stops strict parsing, comments and a dropped byte order mark are no problem for a
+/// document
+fn is_problem( anomaly: &Anomaly ) -> bool {
+    match anomaly.kind( ) {
+        AnomalyKind::Comment | AnomalyKind::ByteOrderMark => false,
+        _ => true,
+    }
+}
+
 impl R_State {
 
+    fn user_agent( &self ) -> &UserAgent {
+        match self {
+            R_State::Comment( u, _, _ ) | R_State::Normal( u ) => u,
+        }
+    }
+
     fn empty_line( self ) -> UserAgent {
 
         let mut ret = match self {
-            R_State::Comment( mut u, s ) => {
-                u.add_comment( "".to_string( ), s );
+            R_State::Comment( mut u, s, span ) => {
+                u.add_comment( "".to_string( ), s, span );
                 u
             },
             R_State::Normal( u ) => {
                 u
             },
         };
-        ret.rules.sort( );
+        ret.rules.sort_by( | a, b |{ a.value.cmp( &b.value ) } );
         ret
     }
 
-    fn comment( self, line: &str ) -> Self {
+    fn comment( self, line: &str, span: Span ) -> Self {
 
         match self {
-            R_State::Comment( u, mut s ) => {
+            R_State::Comment( u, mut s, start ) => {
                 s.push_str( "\n" );
                 s.push_str( line );
-                R_State::Comment( u, s )
+                R_State::Comment( u, s, start.to( span ) )
             },
-            R_State::Normal( u ) => R_State::Comment( u, String::from( line ) ),
+            R_State::Normal( u ) => R_State::Comment( u, String::from( line ), span ),
         }
     }
 
-    fn context_comment( self, context: &str, comment: &str ) -> Self {
+    fn context_comment( self, context: &str, comment: &str, span: Span ) -> Self {
 
         match self {
-            R_State::Comment( mut u, s ) => {
-                u.add_comment( context.to_string( ), s.to_string( ) );
-                u.add_comment( context.to_string( ), comment.to_string( ) );
+            R_State::Comment( mut u, s, start ) => {
+                u.add_comment( context.to_string( ), s.to_string( ), start );
+                u.add_comment( context.to_string( ), comment.to_string( ), span );
                 R_State::Normal( u )
             }
             R_State::Normal( mut u ) => {
-                u.add_comment( context.to_string( ), comment.to_string( ) );
+                u.add_comment( context.to_string( ), comment.to_string( ), span );
                 R_State::Normal( u )
             }
         }
     }
 
-    fn directive_line( self, directive: String, argument: String ) -> Self {
+    /// Handles a directive line inside of a User-agent section, handing back any Host or Sitemap
+    /// directive so that it can be recorded at the top level
+    fn directive_line( self, directive: String, argument: String, span: Span, options: &ParseOptions )
+                       -> ( Self, Option< DirectiveResult > ) {
 
         let mut user_agent;
+        let mut toplevel = None;
 
         match self {
-            R_State::Comment( mut u, s ) => {
+            R_State::Comment( mut u, s, start ) => {
                 let mut context = format!( "{}: {}", directive, argument );
-                u.add_comment( context, s.to_string( ) );
+                u.add_comment( context, s.to_string( ), start );
                 user_agent = u;
             }
             R_State::Normal( u ) => {
                 user_agent = u;
             }
         }
+        user_agent.span = user_agent.span.to( span );
 
-        match parse_directive( &directive, &argument ) {
+        match parse_directive( &directive, &argument, options ) {
             DirectiveResult::Ok_UserAgent( ua ) => {
-                user_agent.add_agent( ua );
+                user_agent.add_agent( ua, span );
             }
             DirectiveResult::Ok_Rule( r ) => {
-                user_agent.add_rule( r );
+                user_agent.add_rule( r, span );
+            }
+            DirectiveResult::Ok_CrawlDelay( d ) => {
+                user_agent.set_crawl_delay( d, &argument, span );
+            }
+            DirectiveResult::Ok_RequestRate( r ) => {
+                user_agent.add_request_rate( r, span );
+            }
+            DirectiveResult::Ok_VisitTime( v ) => {
+                user_agent.add_visit_time( v, span );
             }
             DirectiveResult::Unknown() => {
                 user_agent.add_anomaly(
                     Anomaly::UnknownDirective( directive.to_string( ),
-                                               argument.to_string( ) )
+                                               argument.to_string( ) ), span
                 );
             }
             DirectiveResult::Err_BadArg() => {
                 user_agent.add_anomaly( Anomaly::BadArgument( directive.to_string( ),
-                                                              argument.to_string( ) ) );
+                                                              argument.to_string( ) ), span );
             }
-            _ => {
+            DirectiveResult::Ok_Host( h ) => {
+                //NOTE: Host is so often found inside of a section that we honour it anyway
                 user_agent.add_anomaly(
-                    Anomaly::MissSectionedDirective( directive.to_string( ), argument.to_string( ) )
+                    Anomaly::MissSectionedDirective( directive.to_string( ), argument.to_string( ) ),
+                    span
                 );
+                toplevel = Some( DirectiveResult::Ok_Host( h ) );
+            }
+            DirectiveResult::Ok_Sitemap( s ) => {
+                //NOTE: sections only end at a User-agent line when grouping as RFC 9309 does, where a
+                // Sitemap is a record of its own rather than part of the section before it
+                if options.get_grouping( ) == GroupingMode::BlankLines {
+                    user_agent.add_anomaly(
+                        Anomaly::MissSectionedDirective( directive.to_string( ), argument.to_string( ) ),
+                        span
+                    );
+                }
+                toplevel = Some( DirectiveResult::Ok_Sitemap( s ) );
             }
         }
-        R_State::Normal( user_agent )
+        ( R_State::Normal( user_agent ), toplevel )
     }
 
-    fn bad_casing( self, directive: String, argument: &str ) -> Self {
+    /// Records something which had to be corrected before a line could be read, like odd casing
+    fn fix_up( self, anomaly: Anomaly, span: Span ) -> Self {
 
         match self {
+            R_State::Comment( mut u, s, start ) => {
+                u.add_anomaly( anomaly, span );
+                R_State::Comment( u, s, start )
+            }
             R_State::Normal( mut u ) => {
-                u.add_anomaly( Anomaly::Casing( directive, argument.to_string( ) ) );
+                u.add_anomaly( anomaly, span );
                 R_State::Normal( u )
             }
-            _ => { unreachable!( ) }
         }
 
     }
 
-    fn anomaly( self, line: &str ) -> Self {
+    fn anomaly( self, line: &str, span: Span ) -> Self {
 
         match self {
-            R_State::Comment( mut u, s ) => {
-                u.add_comment( s.to_string( ), line.to_string( ) );
+            R_State::Comment( mut u, s, start ) => {
+                u.add_comment( s.to_string( ), line.to_string( ), start );
+                //NOTE: the line is context for the comment but still has to be seen by strict parsing
+                u.add_anomaly( Anomaly::UnknownFormat( line.to_string( ) ), span );
                 R_State::Normal( u )
             }
             R_State::Normal( mut u ) => {
-                u.add_anomaly( Anomaly::UnknownFormat( line.to_string( ) ) );
+                u.add_anomaly( Anomaly::UnknownFormat( line.to_string( ) ), span );
                 R_State::Normal( u )
             }
         }
@@ -300,8 +512,11 @@ impl State {
     fn empty_line( self ) -> Self {
 
         match self {
-            State::Comment( r, s ) => {
-                State::Comment( r, s )
+            State::Comment( r, s, start ) => {
+                State::Comment( r, s, start )
+            }
+            State::Agent( r, s ) if r.options.get_grouping( ) == GroupingMode::Rfc9309 => {
+                State::Agent( r, s )
             }
             State::Agent( mut r, s ) => {
                 r.add_agent( s.empty_line( ) );
@@ -313,53 +528,65 @@ impl State {
         }
     }
 
-    fn comment( self, line: &str ) -> Self {
+    fn comment( self, line: &str, span: Span ) -> Self {
 
         match self {
-            State::Comment( r, mut s ) => {
+            State::Comment( r, mut s, start ) => {
                 s.push_str( "\n" );
                 s.push_str( line );
-                State::Comment( r, s )
+                State::Comment( r, s, start.to( span ) )
             },
             State::Agent( r, mut s ) => {
-                s = s.comment( line );
+                s = s.comment( line, span );
                 State::Agent( r, s )
             },
-            State::Normal( r ) => State::Comment( r, String::from( line ) ),
+            State::Normal( r ) => State::Comment( r, String::from( line ), span ),
         }
     }
 
-    fn context_comment( self, context: &str, comment: &str ) -> Self {
+    fn context_comment( self, context: &str, comment: &str, span: Span ) -> Self {
 
         match self {
-            State::Comment( mut r, s ) =>{
-                r.add_comment( context.to_string( ), s.to_string( ) );
-                r.add_comment( context.to_string( ), comment.to_string( ) );
+            State::Comment( mut r, s, start ) =>{
+                r.add_comment( context.to_string( ), s.to_string( ), start );
+                r.add_comment( context.to_string( ), comment.to_string( ), span );
                 State::Normal( r )
             }
             State::Agent( r, mut s ) => {
-                s = s.context_comment( context, comment );
+                s = s.context_comment( context, comment, span );
                 State::Agent( r, s )
             }
             State::Normal( mut r ) => {
-                r.add_comment( context.to_string( ), comment.to_string( ) );
+                r.add_comment( context.to_string( ), comment.to_string( ), span );
                 State::Normal( r )
             }
         }
     }
 
-    fn directive_line( self, directive: String , argument: String ) -> Self {
+    fn directive_line( self, directive: String , argument: String, span: Span ) -> Self {
 
         let mut robots;
 
         match self {
-            State::Comment( mut r, s ) => {
+            State::Comment( mut r, s, start ) => {
                 let mut context = format!( "{}: {}", directive, argument );
-                r.add_comment( context, s.to_string( ) );
+                r.add_comment( context, s.to_string( ), start );
                 robots = r;
             }
-            State::Agent( r, mut s ) => {
-                s = s.directive_line( directive, argument );
+            State::Agent( mut r, s ) => {
+                //NOTE: without blank lines to go by a User-agent after a rule is the start of a new section
+                if r.options.get_grouping( ) == GroupingMode::Rfc9309 && directive == "User-agent"
+                    && !s.user_agent( ).is_empty( ) {
+                    r.add_agent( s.empty_line( ) );
+                    return State::Normal( r ).directive_line( directive, argument, span );
+                }
+                let options = r.options;
+                let ( s, toplevel ) = s.directive_line( directive, argument.clone( ), span, &options );
+                match toplevel {
+                    Some( DirectiveResult::Ok_Host( h ) ) => { r.set_preferred_host( h, &argument, span ); }
+                    Some( DirectiveResult::Ok_Sitemap( url ) ) => { r.add_sitemap( url, span ); }
+                    _ => {}
+                }
                 return State::Agent( r, s );
             }
             State::Normal( r ) => {
@@ -367,74 +594,117 @@ impl State {
             }
         }
 
-        match parse_directive( &directive, &argument ) {
+        match parse_directive( &directive, &argument, &robots.options ) {
             DirectiveResult::Ok_UserAgent( ua ) => {
-                State::Agent( robots, R_State::Normal( UserAgent::new( ua ) ) )
+                State::Agent( robots, R_State::Normal( UserAgent::new( ua, span ) ) )
             }
             DirectiveResult::Ok_Rule( r ) => {
-                robots.add_anomaly( Anomaly::OrphanRule( r ) );
+                robots.add_anomaly( Anomaly::OrphanRule( r ), span );
                 State::Normal( robots )
             }
             DirectiveResult::Ok_Sitemap( s ) => {
-                robots.add_sitemap( s );
+                robots.add_sitemap( s, span );
+                State::Normal( robots )
+            }
+            DirectiveResult::Ok_Host( h ) => {
+                robots.set_preferred_host( h, &argument, span );
                 State::Normal( robots )
             }
             DirectiveResult::Err_BadArg( ) => {
                 robots.add_anomaly( Anomaly::BadArgument( directive.to_string( ),
-                                                          argument.to_string( ) ) );
+                                                          argument.to_string( ) ), span );
+                State::Normal( robots )
+            }
+            DirectiveResult::Ok_CrawlDelay( _ ) | DirectiveResult::Ok_RequestRate( _ )
+                | DirectiveResult::Ok_VisitTime( _ ) => {
+                robots.add_anomaly( Anomaly::OrphanDirective( directive.to_string( ),
+                                                              argument.to_string( ) ), span );
                 State::Normal( robots )
             }
-//            DirectiveResult::Ok_RequestRate( r ) => {}
-//            DirectiveResult::Ok_CrawlDelay( d ) = > {}
             DirectiveResult::Unknown( ) => {
                 robots.add_anomaly(
                     Anomaly::UnknownDirective( directive.to_string( ),
-                                               argument.to_string( ) )
+                                               argument.to_string( ) ), span
                 );
                 State::Normal( robots )
             }
         }
     }
 
-    fn bad_casing( self, directive: String, argument: &str ) -> Self {
+    fn fix_up( self, anomaly: Anomaly, span: Span ) -> Self {
 
         match self{
+            State::Comment( mut r, s, start ) => {
+                r.add_anomaly( anomaly, span );
+                State::Comment( r, s, start )
+            }
             State::Normal( mut r ) => {
-                r.add_anomaly( Anomaly::Casing( directive, argument.to_string( ) ) );
+                r.add_anomaly( anomaly, span );
                 State::Normal( r )
             }
             State::Agent( r, mut s ) => {
-                s = s.bad_casing( directive, argument );
+                s = s.fix_up( anomaly, span );
                 State::Agent( r, s )
             }
-            _ => { unreachable!( ) }
         }
     }
 
-    fn anomaly( self, line: &str ) -> Self {
+    fn anomaly( self, line: &str, span: Span ) -> Self {
 
         match self {
-            State::Comment( mut r, s ) => {
-                r.add_comment( line.to_string( ), s.to_string( ) );
-                r.add_unknown( s.to_string( ) );
+            State::Comment( mut r, s, start ) => {
+                r.add_comment( line.to_string( ), s.to_string( ), start );
+                //NOTE: the line is context for the comment but it's the line which can't be read
+                r.add_unknown( line.to_string( ), span );
                 State::Normal( r )
             }
             State::Agent( r, mut s ) => {
-                s = s.anomaly( line );
+                s = s.anomaly( line, span );
                 State::Agent( r, s )
             }
             State::Normal( mut r ) => {
-                r.add_unknown( line.to_string( ) );
+                r.add_unknown( line.to_string( ), span );
                 State::Normal( r )
             }
         }
     }
 
+    fn parts( &self ) -> ( &RobotsParser, Option< &UserAgent > ) {
+        match self {
+            State::Comment( r, _, _ ) | State::Normal( r ) => ( r, None ),
+            State::Agent( r, s ) => ( r, Some( s.user_agent( ) ) ),
+        }
+    }
+
+    /// How many anomalies have been recorded so far at the top level, how many sections have been
+    /// closed and how many anomalies the open section holds, so that whatever a line adds can be found
+    fn recorded( &self ) -> ( usize, usize, usize ) {
+        let ( r, open ) = self.parts( );
+        ( r.anomalies.len( ), r.agents.len( ), open.map_or( 0, | u |{ u.anomalies.len( ) } ) )
+    }
+
+    /// The first problem recorded since .recorded( ) handed back since
+    fn problem( &self, since: ( usize, usize, usize ) ) -> Option< &Spanned< Anomaly > > {
+        let ( r, open ) = self.parts( );
+        let ( top, closed, section ) = since;
+        let none: &[ Spanned< Anomaly > ] = &[ ];
+
+        //NOTE: a line closes at most one section, which was the open section when since was taken
+        let ( closing, opened ) = match r.agents.get( closed ) {
+            Some( u ) => ( &u.anomalies[ section.. ], open.map_or( none, | u |{ &u.anomalies[ .. ] } ) ),
+            None => ( none, open.map_or( none, | u |{ &u.anomalies[ section.. ] } ) ),
+        };
+
+        r.anomalies[ top.. ].iter( ).chain( closing ).chain( opened )
+            .filter( | a |{ is_problem( a ) } )
+            .min_by_key( | a |{ ( a.span.line, a.span.start ) } )
+    }
+
     fn eof( self ) -> RobotsParser {
 
         let mut ret = match self {
-            State::Comment( mut r, s ) => {
-                r.add_comment( "[EOF]".to_string( ), s.to_string( ) );
+            State::Comment( mut r, s, start ) => {
+                r.add_comment( "[EOF]".to_string( ), s.to_string( ), start );
                 r
             }
             State::Agent( mut r, s ) => {
@@ -445,26 +715,191 @@ impl State {
                 r
             }
         };
+        ret.merge_agents( );
         ret.agents.sort( );
         ret
     }
 }
 
 impl RobotsParser {
-    //HACK: This is the function to understand if you want to add a feature
-    pub fn parse< S: Into<String> >( host: BaseUrl, text: S ) -> Self {
-        let text = text.into( ); //Not a one-liner to appease lifetimes
-        let lines = text.lines( );
-        let ret = RobotsParser{
-            host: host,
-            sitemaps: Vec::new( ),
-            agents: Vec::new( ),
-            anomalies: Vec::new( ),
+
+    /// Folds every User-agent section in to the first section naming the same agents
+    fn merge_agents( &mut self ) {
+        let mut merged: Vec< UserAgent > = Vec::with_capacity( self.agents.len( ) );
+
+        for agent in self.agents.drain( .. ) {
+            match merged.iter_mut( ).find( | m |{ m.same_names( &agent ) } ) {
+                Some( earlier ) => { earlier.merge( agent ); }
+                None => { merged.push( agent ); }
+            }
+        }
+
+        self.agents = merged;
+    }
+
+    /// Records the first Host directive seen as the preferred mirror, any which follow are anomalous
+    fn set_preferred_host( &mut self, host: String, argument: &str, span: Span ) {
+        if self.preferred_host.is_some( ) {
+            self.add_anomaly( Anomaly::ExtraHost( argument.to_string( ) ), span );
+            return;
+        }
+
+        let url = if host.contains( "://" ) {
+            format!( "{}/", host )
+        } else {
+            format!( "{}://{}/", self.host.scheme( ), host )
         };
+        match BaseUrl::try_from( url.as_str( ) ) {
+            Ok( u ) => { self.preferred_host = Some( Spanned::new( u, span ) ); }
+            Err( _e ) => {
+                self.add_anomaly( Anomaly::BadArgument( "Host".to_string( ), argument.to_string( ) ),
+                                  span );
+            }
+        }
+    }
+
+    pub fn parse< S: Into<String> >( host: BaseUrl, text: S ) -> Self {
+        Self::parse_with( host, text, &ParseOptions::default( ) )
+    }
+
+    /// Parses a robots.txt document according to the supplied ParseOptions
+    pub fn parse_with< S: Into<String> >( host: BaseUrl, text: S, options: &ParseOptions ) -> Self {
+        Self::parse_document( host, text.into( ), *options, false ).0
+    }
+
+    /// Parses a robots.txt document with ParseOptions::verbatim( ), failing with the first problem in the
+    /// document rather than recording it. Comments are the only Anomaly which isn't a problem.
+    pub fn parse_strict< S: Into<String> >( host: BaseUrl, text: S ) -> Result< Self, ParseError > {
+        Self::parse_strict_with( host, text, &ParseOptions::verbatim( ) )
+    }
+
+    /// Parses a robots.txt document according to the supplied ParseOptions, failing with the first problem
+    /// in the document the same way as .parse_strict( ). Reading stops at the line the problem is on.
+    pub fn parse_strict_with< S: Into<String> >( host: BaseUrl, text: S, options: &ParseOptions )
+                                                 -> Result< Self, ParseError > {
+        let ( ret, problem ) = Self::parse_document( host, text.into( ), *options, true );
+
+        //NOTE: merged sections and a cut are only recorded once the whole document has been read
+        let first = problem.or_else( ||{
+            ret.get_all_anomalies_spanned( ).into_iter( )
+                .filter( | a |{ is_problem( a ) } )
+                .min_by_key( | a |{ ( a.span.line, a.span.start ) } )
+                .cloned( )
+        } );
+
+        match first {
+            Some( anomaly ) => Err( ParseError{
+                line: anomaly.span.line,
+                kind: anomaly.kind( ),
+                anomaly: anomaly,
+            } ),
+            None => Ok( ret ),
+        }
+    }
+
+    /// Parses a robots.txt document which hasn't been decoded yet. Any bytes which aren't valid UTF-8 are
+    /// replaced by U+FFFD and recorded as an InvalidEncoding Anomaly, the rest of the document is read
+    /// the same as by .parse( ). Spans are counted in the decoded document.
+    pub fn parse_bytes( host: BaseUrl, bytes: &[u8] ) -> Self {
+        Self::parse_bytes_with( host, bytes, &ParseOptions::default( ) )
+    }
+
+    /// Parses a robots.txt document which hasn't been decoded yet the same way as .parse_bytes( ), but
+    /// according to the supplied ParseOptions
+    pub fn parse_bytes_with( host: BaseUrl, bytes: &[u8], options: &ParseOptions ) -> Self {
+        //NOTE: the limit is applied before decoding so a hostile document is never decoded in full
+        let cut = line_boundary( bytes, options.get_max_bytes( ) );
+        let ( bytes, dropped ) = bytes.split_at( cut );
+
+        let mut text = String::with_capacity( bytes.len( ) );
+        let mut invalid = Vec::new( );
+        let mut rest = bytes;
+        //NOTE: lines are counted as the text is decoded, counting them afresh for every invalid sequence
+        // would take time quadratic in the size of the document
+        let mut line = 1;
+
+        loop {
+            match str::from_utf8( rest ) {
+                Ok( valid ) => {
+                    text.push_str( valid );
+                    line += valid.bytes( ).filter( | byte |{ *byte == b'\n' } ).count( );
+                    break;
+                }
+                Err( e ) => {
+                    let ( valid, after ) = rest.split_at( e.valid_up_to( ) );
+                    text.push_str( str::from_utf8( valid ).unwrap( ) );
+                    line += valid.iter( ).filter( | byte |{ **byte == b'\n' } ).count( );
+
+                    //NOTE: a sequence cut short by the end of the document has no error_len
+                    let length = e.error_len( ).unwrap_or( after.len( ) );
+                    let hex: Vec< String > = after[ ..length ].iter( )
+                        .map( | byte |{ format!( "{:02X}", byte ) } ).collect( );
+                    let start = text.len( );
+                    text.push( '\u{fffd}' );
+                    let span = Span{ line: line, start: start, end: text.len( ) };
+                    invalid.push( ( hex.join( " " ), span ) );
+
+                    rest = &after[ length.. ];
+                }
+            }
+        }
+
+        let length = text.len( );
+
+        let ( mut ret, _ ) = Self::parse_document( host, text, options.max_bytes( MAX ), false );
+        ret.options = *options;
+        for ( hex, span ) in invalid {
+            ret.add_anomaly( Anomaly::InvalidEncoding( hex ), span );
+        }
+        if !dropped.is_empty( ) {
+            ret.add_anomaly( Anomaly::Truncated( cut ),
+                             Span{ line: line, start: length, end: length + dropped.len( ) } );
+        }
+        ret
+    }
+
+    //HACK: This is the function to understand if you want to add a feature
+    //NOTE: when strict the first problem found stops the reading and is handed back alongside whatever
+    // was read up to that point
+    fn parse_document( host: BaseUrl, mut text: String, options: ParseOptions, strict: bool )
+                       -> ( Self, Option< Spanned< Anomaly > > ) {
+        let length = text.len( );
+        let cut = line_boundary( text.as_bytes( ), options.get_max_bytes( ) );
+        text.truncate( cut );
+
+        let lines = text.split( '\n' );
+        let ret = Self::empty( host, options );
 
         let mut state = State::Normal( ret );
+        let mut offset = 0;
+        let mut last_line = 1;
+        let mut since = state.recorded( );
+
+        for ( number, raw ) in lines.enumerate( ) {
+            //NOTE: checked as each line starts so none of the ways through the last line are missed
+            if strict {
+                let problem = state.problem( since ).cloned( );
+                if problem.is_some( ) {
+                    return ( state.eof( ), problem );
+                }
+                since = state.recorded( );
+            }
+
+            let _line = raw.trim_end_matches( '\r' );
+            let span = Span{ line: number + 1, start: offset, end: offset + _line.len( ) };
+            offset += raw.len( ) + 1;
+            last_line = number + 1;
+
+            //NOTE: a byte order mark isn't whitespace as far as trim( ) is concerned and would otherwise
+            // end up as part of the first directive
+            let _line = if number == 0 && options.strips_bom( ) && _line.starts_with( '\u{feff}' ) {
+                let bom = Span{ line: 1, start: 0, end: '\u{feff}'.len_utf8( ) };
+                state = state.fix_up( Anomaly::ByteOrderMark( String::from( "EF BB BF" ) ), bom );
+                _line.trim_start_matches( '\u{feff}' )
+            } else {
+                _line
+            };
 
-        for _line in lines {
             //NOTE: in both of the split_at directives the split character goes into r
             let mut line = _line.trim( ); //clear any whitespace
 
@@ -480,45 +915,82 @@ impl RobotsParser {
              * Comments
              ******/
             if line.starts_with( "#" ) {
-                state = state.comment( line );
+                if options.collects_comments( ) {
+                    state = state.comment( line, span );
+                }
                 continue;
             } else if line.contains( "#" ) {
                 let ( l, r ) = line.split_at( line.find( "#" ).unwrap( ) );
-                state = state.context_comment( l, r );
+                if options.collects_comments( ) {
+                    state = state.context_comment( l, r, span );
+                }
                 line = l.trim( );
             }
 
             /***********
              * Directives
              ******/
-            if line.contains( ":" ) {
-                let ( _l, r ) = line.split_at( line.find( ":" ).unwrap( ) );
-                let l = if _l.starts_with( | c: char |( c.is_lowercase( ) ) )
-                    || _l.get( 1.. ).unwrap( ).find( |c: char| {c.is_uppercase( ) } ).is_some( ) {
-
-                        state = state.bad_casing( _l.to_string( ), r );
-
-                        let mut proper_case = _l.to_ascii_lowercase( );
-                        proper_case.get_mut(0..1).map( | c |{ c.make_ascii_uppercase( ); &*c } );
-                        proper_case
-                    } else {
-                        _l.to_string( )
-                    };
-                state = state.directive_line(
-                    l,
-                    r.trim_left_matches( | c:char | {
-                        c.is_whitespace( ) || c == ':'
-                    } ).to_string( )
-                );
+            let split = if options.fixes_misspellings( ) {
+                split_directive( line )
+            } else {
+                line.find( ':' ).map( | i |{ ( &line[ ..i ], &line[ i.. ], true ) } )
+            };
+            if let Some( ( _l, r, separated ) ) = split {
+                let argument = r.trim_left_matches( | c:char | {
+                    c.is_whitespace( ) || c == ':'
+                } );
+                let recognised = recognise_directive( _l ).filter( | known |{
+                    options.fixes_misspellings( ) || _l.eq_ignore_ascii_case( known )
+                } );
+                let l = match recognised {
+                    //NOTE: a misspelt name, a space before the colon or a missing colon
+                    Some( known ) if !_l.eq_ignore_ascii_case( known ) || !separated => {
+                        state = state.fix_up( Anomaly::Misspelling( line.to_string( ),
+                                                                    format!( "{}: {}", known, argument ) ),
+                                              span );
+                        known.to_string( )
+                    }
+                    Some( known ) if _l != known => {
+                        state = state.fix_up( Anomaly::Casing( _l.to_string( ), r.to_string( ) ), span );
+                        if options.fixes_casing( ) { known.to_string( ) } else { _l.to_string( ) }
+                    }
+                    Some( known ) => known.to_string( ),
+                    None if _l.starts_with( | c: char |( c.is_lowercase( ) ) )
+                        || _l.chars( ).skip( 1 ).any( |c: char| {c.is_uppercase( ) } ) => {
+
+                        state = state.fix_up( Anomaly::Casing( _l.to_string( ), r.to_string( ) ), span );
+
+                        if options.fixes_casing( ) {
+                            let mut proper_case = _l.to_ascii_lowercase( );
+                            proper_case.get_mut(0..1).map( | c |{ c.make_ascii_uppercase( ); &*c } );
+                            proper_case
+                        } else {
+                            _l.to_string( )
+                        }
+                    }
+                    None => _l.to_string( ),
+                };
+                state = state.directive_line( l, argument.to_string( ), span );
             } else {
                 /***********
                  * Everything else
                  ******/
-                state = state.anomaly( line );
+                state = state.anomaly( line, span );
+            }
+        }
+
+        if strict {
+            let problem = state.problem( since ).cloned( );
+            if problem.is_some( ) {
+                return ( state.eof( ), problem );
             }
         }
 
-        state.eof( )
+        let mut ret = state.eof( );
+        if cut < length {
+            ret.add_anomaly( Anomaly::Truncated( cut ), Span{ line: last_line, start: cut, end: length } );
+        }
+        ( ret, None )
     }
 }
 
@@ -579,6 +1051,7 @@ mod tests {
         rule_vec_b.sort( );
 
         assert_eq!( rule_vec_a, rule_vec_b );
+        assert_eq!( rule_vec_a, vec![ rule_d, rule_c, rule_a, rule_b ] );
     }
 
     /***********
@@ -586,12 +1059,42 @@ mod tests {
      ******/
     #[test]
     fn useragent_ordering( ) {
-        let ua_1 = UserAgent::new( String::from( "*" ) );
-        let ua_2 = UserAgent::new( String::from( "foogle" ) );
-        let ua_3 = UserAgent::new( String::from( "foogle-news" ) );
+        let ua_1 = UserAgent::new( String::from( "*" ), Span::default( ) );
+        let ua_2 = UserAgent::new( String::from( "foogle" ), Span::default( ) );
+        let ua_3 = UserAgent::new( String::from( "foogle-news" ), Span::default( ) );
 
         assert!( ua_1 > ua_2 );
         assert!( ua_2 > ua_3 );
 
     }
+
+    /***********
+     * Parsing
+     ******/
+    #[test]
+    fn line_boundaries( ) {
+        assert_eq!( line_boundary( b"ab\ncd\nef", 9 ), 8 );
+        assert_eq!( line_boundary( b"ab\ncd\nef", 6 ), 6 );
+        assert_eq!( line_boundary( b"ab\ncd\nef", 5 ), 6 );
+        assert_eq!( line_boundary( b"ab\ncd\nef", 4 ), 3 );
+        assert_eq!( line_boundary( b"abcdef", 3 ), 0 );
+    }
+
+    #[test]
+    fn directive_spellings( ) {
+        assert_eq!( recognise_directive( "Dissallow" ), Some( "Disallow" ) );
+        assert_eq!( recognise_directive( "User agent" ), Some( "User-agent" ) );
+        assert_eq!( recognise_directive( "site-map" ), Some( "Sitemap" ) );
+        assert_eq!( recognise_directive( "Noindex" ), None );
+
+        assert_eq!( split_directive( "Disallow: /a" ), Some( ( "Disallow", ": /a", true ) ) );
+        assert_eq!( split_directive( "user-agent : *" ), Some( ( "user-agent", ": *", false ) ) );
+        assert_eq!( split_directive( "Disallow /a:b" ), Some( ( "Disallow", " /a:b", false ) ) );
+        assert_eq!( split_directive( "User agent bot" ), Some( ( "User agent", " bot", false ) ) );
+        assert_eq!( split_directive( "Noindex: /a" ), Some( ( "Noindex", ": /a", true ) ) );
+        assert_eq!( split_directive( "Just some words" ), None );
+        assert_eq!( split_directive( "Disallow" ), None );
+        assert_eq!( split_directive( "User agent" ), None );
+        assert_eq!( split_directive( ": /nameless" ), Some( ( "", ": /nameless", true ) ) );
+    }
 }
@@ -1,5 +1,75 @@
 //
-pub fn match_with_asterisk( haystack: &str, needle: &str ) -> bool {
+/// Splits a trailing '$' end-of-path anchor from a pattern. A '$' anywhere else is taken literally.
+pub fn split_anchor( pattern: &str ) -> ( &str, bool ) {
+    match pattern.strip_suffix( '$' ) {
+        Some( stripped ) => ( stripped, true ),
+        None => ( pattern, false ),
+    }
+}
+
+/// Splits a path from the query following its first '?', if there is one
+pub fn split_query( path: &str ) -> ( &str, Option< &str > ) {
+    match path.find( '?' ) {
+        Some( i ) => ( &path[ ..i ], Some( &path[ i + 1.. ] ) ),
+        None => ( path, None ),
+    }
+}
+
+fn is_unreserved( byte: u8 ) -> bool {
+    byte.is_ascii_alphanumeric( ) || byte == b'-' || byte == b'.' || byte == b'_' || byte == b'~'
+}
+
+fn hex_value( byte: u8 ) -> Option< u8 > {
+    ( byte as char ).to_digit( 16 ).map( | d |{ d as u8 } )
+}
+
+/// Brings a path, or a pattern, to a single spelling so that equivalent paths compare equal as described
+/// in RFC 9309. Escaped unreserved characters are decoded, the hex digits of any other escape are
+/// upper-cased and anything outside of ASCII is percent-encoded.
+pub fn normalise_path( path: &str ) -> String {
+
+    let bytes = path.as_bytes( );
+    let mut ret = String::with_capacity( bytes.len( ) );
+    let mut i = 0;
+
+    while i < bytes.len( ) {
+        let byte = bytes[ i ];
+        let escaped = if byte == b'%' && i + 2 < bytes.len( ) {
+            match ( hex_value( bytes[ i + 1 ] ), hex_value( bytes[ i + 2 ] ) ) {
+                ( Some( high ), Some( low ) ) => Some( high * 16 + low ),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        match escaped {
+            Some( decoded ) if is_unreserved( decoded ) => {
+                ret.push( decoded as char );
+                i += 3;
+            }
+            Some( decoded ) => {
+                ret.push_str( &format!( "%{:02X}", decoded ) );
+                i += 3;
+            }
+            None if byte.is_ascii( ) => {
+                //NOTE: a '%' which doesn't start an escape is left alone rather than guessed at
+                ret.push( byte as char );
+                i += 1;
+            }
+            None => {
+                ret.push_str( &format!( "%{:02X}", byte ) );
+                i += 1;
+            }
+        }
+    }
+
+    ret
+}
+
+/// Matches a single path segment against a needle, where any asterisk stands in for any run of
+/// characters. Anchors aren't understood here, a '$' is taken literally.
+pub fn match_segment( haystack: &str, needle: &str ) -> bool {
 
     if !needle.contains( '*' ) {
         return haystack == needle;
@@ -9,21 +79,38 @@ pub fn match_with_asterisk( haystack: &str, needle: &str ) -> bool {
         return true;
     }
 
-    let mut loc = 0;
-    let segments = if !needle.starts_with( '*' ) {
-        let mut _segments = needle.split( '*' );
-        let first_seg = _segments.next( ).unwrap( );
-        if !haystack.starts_with( first_seg ) { return false; }
-        loc += first_seg.len( );
-        _segments
-    } else {
-        needle.split( '*' )
-    };
+    match_asterisks( haystack, needle, false )
+}
+
+/// Matches a robots.txt path against the start of the haystack, as described in RFC 9309, where any
+/// asterisk stands in for any run of characters and a trailing '$' anchors the path to the end
+pub fn match_prefix( haystack: &str, pattern: &str ) -> bool {
+
+    let ( pattern, anchored ) = split_anchor( pattern );
+
+    if !pattern.contains( '*' ) {
+        return if anchored { haystack == pattern } else { haystack.starts_with( pattern ) };
+    }
+
+    match_asterisks( haystack, pattern, anchored )
+}
+
+fn match_asterisks( haystack: &str, needle: &str, anchored: bool ) -> bool {
+
+    let mut _segments = needle.split( '*' );
+    let first_seg = _segments.next( ).unwrap( );
+    if !haystack.starts_with( first_seg ) { return false; }
+    let mut loc = first_seg.len( );
+
+    //NOTE: when anchored the final segment has to be found at the very end of the haystack rather than
+    // at its first occurance
+    let mut segments: Vec< &str > = _segments.collect( );
+    let last_seg = if anchored { segments.pop( ) } else { None };
 
     for seg in segments {
         if seg == "" { /* DO NOTHING */ }
         else {
-            match prefix_asterisk( seg, &haystack[ loc.. ], 0 ) {
+            match haystack[ loc.. ].find( seg ) {
                 Some( i ) => {
                     loc += i + seg.len( );
                 }
@@ -34,20 +121,10 @@ pub fn match_with_asterisk( haystack: &str, needle: &str ) -> bool {
         }
     }
 
-    true
-}
-
-fn prefix_asterisk( suffix: &str, haystack:&str, loc: usize ) -> Option< usize > {
-
-    if haystack.len( ) < suffix.len( ) {
-        return None;
-    }
-
-    if haystack.starts_with( suffix ) {
-        return Some( loc );
+    match last_seg {
+        Some( seg ) => haystack.len( ) - loc >= seg.len( ) && haystack.ends_with( seg ),
+        None => true,
     }
-
-    return prefix_asterisk( suffix, &haystack[1..], loc + 1 );
 }
 
 
@@ -58,44 +135,97 @@ mod tests{
 
     #[test]
     fn asterisk_only( ) {
-        assert!( match_with_asterisk( "This can be literally anything", "*" ) );
-        assert!( match_with_asterisk( "No really, anything +-=*//\\", "*" ) );
-        assert!( match_with_asterisk( "", "*" ) );
+        assert!( match_segment( "This can be literally anything", "*" ) );
+        assert!( match_segment( "No really, anything +-=*//\\", "*" ) );
+        assert!( match_segment( "", "*" ) );
     }
 
     #[test]
     fn no_asterisk( ) {
-        assert!( match_with_asterisk( "No Asterisk", "No Asterisk" ) );
+        assert!( match_segment( "No Asterisk", "No Asterisk" ) );
     }
 
     #[test]
     fn leading_asterisk( ) {
-        assert!( match_with_asterisk( "Target", "*Target" ) );
-        assert!( match_with_asterisk( "Some things we don't care about and the Target", "*Target" ) );
-        assert!( match_with_asterisk( "The Target needs to be last to match", "*Target" ) );
-        assert!( !match_with_asterisk( "Really just things we don't care about", "*Target" ) );
+        assert!( match_segment( "Target", "*Target" ) );
+        assert!( match_segment( "Some things we don't care about and the Target", "*Target" ) );
+        assert!( match_segment( "The Target needs to be last to match", "*Target" ) );
+        assert!( !match_segment( "Really just things we don't care about", "*Target" ) );
     }
 
     #[test]
     fn trailing_asterisk( ) {
-        assert!( match_with_asterisk( "Target", "Target*" ) );
-        assert!( match_with_asterisk( "Target and some things we don't care about", "Target*" ) );
-        assert!( !match_with_asterisk( "We care about Target, but this won't match", "Target*" ) );
-        assert!( !match_with_asterisk( "No instance of the string we want", "Target*" ) );
+        assert!( match_segment( "Target", "Target*" ) );
+        assert!( match_segment( "Target and some things we don't care about", "Target*" ) );
+        assert!( !match_segment( "We care about Target, but this won't match", "Target*" ) );
+        assert!( !match_segment( "No instance of the string we want", "Target*" ) );
     }
 
     #[test]
     fn segmented_asterisks( ) {
-        assert!( match_with_asterisk( "A bit more complex, but still works",
-                                      "A bit*but still*work*" ) );
-        assert!( !match_with_asterisk( "more complex by a bit and doesn't work",
-                                       "*a bit*more complex*work*" ) );
+        assert!( match_segment( "A bit more complex, but still works",
+                                "A bit*but still*work*" ) );
+        assert!( !match_segment( "more complex by a bit and doesn't work",
+                                 "*a bit*more complex*work*" ) );
     }
 
     #[test]
     fn redundant_asterisks( ) {
-        assert!( match_with_asterisk( "This should match", "**sh**ma*" ) );
-        assert!( !match_with_asterisk( "Doesn't match", "**at**oe*" ) );
+        assert!( match_segment( "This should match", "**sh**ma*" ) );
+        assert!( !match_segment( "Doesn't match", "**at**oe*" ) );
+    }
+
+    #[test]
+    fn prefix_match( ) {
+        assert!( match_prefix( "/page.html", "/page" ) );
+        assert!( match_prefix( "/page", "/page" ) );
+        assert!( !match_prefix( "/pag", "/page" ) );
+        assert!( match_prefix( "/fish/salmon.php", "/fish*.php" ) );
+        assert!( !match_prefix( "/Fish.php", "/fish*.php" ) );
+    }
+
+    #[test]
+    fn anchored( ) {
+        assert!( match_prefix( "/report.pdf", "/*.pdf$" ) );
+        assert!( !match_prefix( "/report.pdf.old", "/*.pdf$" ) );
+        assert!( match_prefix( "/exact", "/exact$" ) );
+        assert!( !match_prefix( "/exactly", "/exact$" ) );
+    }
+
+    #[test]
+    fn anchored_asterisks( ) {
+        assert!( match_prefix( "/a/file.pdf", "/*.pdf$" ) );
+        assert!( !match_prefix( "/a/file.pdf?download", "/*.pdf$" ) );
+        assert!( !match_prefix( "/file.pdf.bak", "/*.pdf$" ) );
+        assert!( match_prefix( "/a.pdf/b.pdf", "/*.pdf$" ) );
+        assert!( match_prefix( "/anything/at/all", "/*$" ) );
+        assert!( match_prefix( "/aXbY", "/a*b*$" ) );
+        assert!( !match_prefix( "/ab", "/a*b*c$" ) );
+    }
+
+    #[test]
+    fn literal_dollar( ) {
+        assert!( match_prefix( "/price$/list", "/price$/" ) );
+        assert!( !match_prefix( "/price/list", "/price$/" ) );
+        assert!( match_segment( "a$b", "a$b" ) );
+        assert!( match_prefix( "/$5/deals", "/$*/deals" ) );
+    }
+
+    #[test]
+    fn queries( ) {
+        assert_eq!( split_query( "/search?q=rust" ), ( "/search", Some( "q=rust" ) ) );
+        assert_eq!( split_query( "/a?b?c" ), ( "/a", Some( "b?c" ) ) );
+        assert_eq!( split_query( "/plain" ), ( "/plain", None ) );
+    }
+
+    #[test]
+    fn normalisation( ) {
+        assert_eq!( normalise_path( "/caf%c3%a9" ), "/caf%C3%A9" );
+        assert_eq!( normalise_path( "/café" ), "/caf%C3%A9" );
+        assert_eq!( normalise_path( "/%7Euser/%41bc" ), "/~user/Abc" );
+        assert_eq!( normalise_path( "/a%2fb%2A" ), "/a%2Fb%2A" );
+        assert_eq!( normalise_path( "/100%/%zz/%" ), "/100%/%zz/%" );
+        assert_eq!( normalise_path( "/*.pdf$" ), "/*.pdf$" );
     }
 
 }
@@ -0,0 +1,195 @@
+//
+// Request-rate, Visit-time and the combined politeness answer built from them and Crawl-delay
+
+use std::fmt::{ Formatter, Display };
+use std::fmt::Result as DisplayResult;
+
+use std::time::Duration;
+
+/// A window of the day, in UTC, during which a site would prefer to be visited. Windows given with an
+/// end before their start are understood to wrap past midnight.
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub struct VisitTime {
+    /// The (hour, minute) the window opens
+    pub start: ( u8, u8 ),
+    /// The (hour, minute) the window closes
+    pub end: ( u8, u8 ),
+}
+
+impl VisitTime {
+
+    fn parse_clock( clock: &str ) -> Option< ( u8, u8 ) > {
+        if clock.len( ) != 4 || !clock.chars( ).all( | c |{ c.is_ascii_digit( ) } ) {
+            return None;
+        }
+        let hour = clock[ ..2 ].parse::< u8 >( ).ok( )?;
+        let minute = clock[ 2.. ].parse::< u8 >( ).ok( )?;
+
+        if hour < 24 && minute < 60 { Some( ( hour, minute ) ) } else { None }
+    }
+
+    /// Reads a window in the HHMM-HHMM format used by both Visit-time and Request-rate
+    pub fn parse( window: &str ) -> Option< Self > {
+        let mut clocks = window.trim( ).split( '-' );
+        let start = Self::parse_clock( clocks.next( )?.trim( ) )?;
+        let end = Self::parse_clock( clocks.next( )?.trim( ) )?;
+
+        if clocks.next( ).is_some( ) {
+            None
+        } else {
+            Some( VisitTime{ start: start, end: end } )
+        }
+    }
+
+    /// Whether the supplied UTC time of day falls inside of this window
+    pub fn contains( &self, hour: u8, minute: u8 ) -> bool {
+        let time = ( hour, minute );
+        if self.start <= self.end {
+            self.start <= time && time <= self.end
+        } else {
+            self.start <= time || time <= self.end
+        }
+    }
+}
+
+impl Display for VisitTime {
+    fn fmt( &self, formatter: &mut Formatter ) -> DisplayResult {
+        write!( formatter, "{:02}{:02}-{:02}{:02}",
+                self.start.0, self.start.1, self.end.0, self.end.1 )
+    }
+}
+
+/// A number of requests a site will accept over some period, possibly only during a window of the day
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub struct RequestRate {
+    pub requests: u32,
+    pub per: Duration,
+    pub window: Option< VisitTime >,
+}
+
+impl RequestRate {
+
+    fn parse_period( period: &str ) -> Option< Duration > {
+        let ( count, unit ) = match period.find( | c: char |{ !c.is_ascii_digit( ) } ) {
+            Some( i ) => period.split_at( i ),
+            None => ( period, "s" ),
+        };
+        let count = count.parse::< u64 >( ).ok( )?;
+        let unit = match unit.to_ascii_lowercase( ).as_str( ) {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 60 * 60 * 24,
+            _ => { return None; }
+        };
+
+        if count == 0 { None } else { count.checked_mul( unit ).map( Duration::from_secs ) }
+    }
+
+    /// Reads a Request-rate argument of the form n/T, where T is a number of seconds optionally suffixed
+    /// with one of the units s, m, h or d, followed by an optional HHMM-HHMM window
+    pub fn parse( argument: &str ) -> Option< Self > {
+        let mut parts = argument.split_whitespace( );
+        let mut rate = parts.next( )?.split( '/' );
+        let window = match parts.next( ) {
+            Some( w ) => Some( VisitTime::parse( w )? ),
+            None => None,
+        };
+        if parts.next( ).is_some( ) {
+            return None;
+        }
+
+        let requests = rate.next( )?.parse::< u32 >( ).ok( )?;
+        let per = Self::parse_period( rate.next( )? )?;
+        if requests == 0 || rate.next( ).is_some( ) {
+            return None;
+        }
+
+        Some( RequestRate{ requests: requests, per: per, window: window } )
+    }
+
+    /// The time which should pass between two requests to keep to this rate
+    pub fn interval( &self ) -> Duration {
+        self.per / self.requests
+    }
+
+    /// Whether this rate is in effect at the supplied UTC time of day
+    pub fn applies_at( &self, hour: u8, minute: u8 ) -> bool {
+        match self.window {
+            Some( window ) => window.contains( hour, minute ),
+            None => true,
+        }
+    }
+}
+
+impl Display for RequestRate {
+    fn fmt( &self, formatter: &mut Formatter ) -> DisplayResult {
+        write!( formatter, "{}/{}s", self.requests, self.per.as_secs( ) )?;
+        if let Some( window ) = self.window {
+            write!( formatter, " {}", window )?;
+        }
+        Ok( ( ) )
+    }
+}
+
+/// Everything a robots.txt file asks of an agent regarding how often, and when, it should visit
+#[derive( Debug, Clone, PartialEq, Eq, Default )]
+pub struct Politeness {
+    pub crawl_delay: Option< Duration >,
+    pub request_rates: Vec< RequestRate >,
+    pub visit_times: Vec< VisitTime >,
+}
+
+impl Politeness {
+
+    /// The shortest time which should pass between two requests made at the supplied UTC time of day,
+    /// taking the strictest of the Crawl-delay and any Request-rate in effect. Returns None when the
+    /// file places no limit on the agent at that time.
+    pub fn interval_at( &self, hour: u8, minute: u8 ) -> Option< Duration > {
+        self.request_rates.iter( ).filter( | rate |{ rate.applies_at( hour, minute ) } )
+            .map( | rate |{ rate.interval( ) } )
+            .chain( self.crawl_delay )
+            .max( )
+    }
+
+    /// Whether the supplied UTC time of day falls inside of one of the requested Visit-time windows. A
+    /// file without any Visit-time directive may be visited at any time.
+    pub fn may_visit_at( &self, hour: u8, minute: u8 ) -> bool {
+        self.visit_times.is_empty( ) ||
+            self.visit_times.iter( ).any( | window |{ window.contains( hour, minute ) } )
+    }
+}
+
+
+#[cfg( test )]
+mod tests{
+
+    use super::*;
+
+    #[test]
+    fn visit_time( ) {
+        let day = VisitTime::parse( "0600-0845" ).unwrap( );
+        let night = VisitTime::parse( "2300-0130" ).unwrap( );
+
+        assert!( day.contains( 7, 30 ) );
+        assert!( !day.contains( 9, 0 ) );
+        assert!( night.contains( 0, 15 ) );
+        assert!( !night.contains( 12, 0 ) );
+        assert_eq!( VisitTime::parse( "2400-0100" ), None );
+        assert_eq!( VisitTime::parse( "600-845" ), None );
+    }
+
+    #[test]
+    fn request_rate( ) {
+        let plain = RequestRate::parse( "1/5" ).unwrap( );
+        let minutes = RequestRate::parse( "3/1m 0600-0845" ).unwrap( );
+
+        assert_eq!( plain.interval( ), Duration::from_secs( 5 ) );
+        assert_eq!( minutes.interval( ), Duration::from_secs( 20 ) );
+        assert!( minutes.applies_at( 6, 0 ) && !minutes.applies_at( 12, 0 ) );
+        assert_eq!( RequestRate::parse( "0/5s" ), None );
+        assert_eq!( RequestRate::parse( "1/5y" ), None );
+        assert_eq!( RequestRate::parse( "fast" ), None );
+        assert_eq!( RequestRate::parse( "1/999999999999999999d" ), None );
+    }
+}
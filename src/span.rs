@@ -0,0 +1,69 @@
+//
+// Locations in a robots.txt document
+
+use std::fmt::{ Formatter, Display };
+use std::fmt::Result as DisplayResult;
+use std::ops::{ Deref, Range };
+
+/// Where in a robots.txt document something was found. Lines are counted from 1 and the byte range
+/// covers every line the thing was found across, without their line endings.
+#[derive( Debug, Clone, Copy, PartialEq, Eq, Default )]
+pub struct Span {
+    /// The line the thing starts on
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+
+    pub fn bytes( &self ) -> Range< usize > {
+        self.start..self.end
+    }
+
+    /// A Span reaching from the start of this one to the end of a later one
+    pub fn to( &self, later: Span ) -> Span {
+        Span{
+            line: self.line,
+            start: self.start,
+            end: later.end,
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt( &self, formatter: &mut Formatter ) -> DisplayResult {
+        write!( formatter, "{}", self.line )
+    }
+}
+
+/// Anything read out of a robots.txt document alongside the Span it was read from. Derefs to the value
+/// so it can be used in much the same way as the bare value.
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub struct Spanned< T > {
+    pub value: T,
+    pub span: Span,
+}
+
+impl< T > Spanned< T > {
+    pub fn new( value: T, span: Span ) -> Self {
+        Spanned{
+            value: value,
+            span: span,
+        }
+    }
+}
+
+impl< T > Deref for Spanned< T > {
+    type Target = T;
+
+    fn deref( &self ) -> &T {
+        &self.value
+    }
+}
+
+impl< T: Display > Display for Spanned< T > {
+    fn fmt( &self, formatter: &mut Formatter ) -> DisplayResult {
+        write!( formatter, "{}: {}", self.span, self.value )
+    }
+}
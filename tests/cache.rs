@@ -0,0 +1,194 @@
+extern crate rs_pider_robots;
+extern crate base_url;
+
+use std::panic::{ self, AssertUnwindSafe };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::time::Duration;
+
+use rs_pider_robots::*;
+
+use base_url::BaseUrl;
+use base_url::TryFrom;
+
+fn url( url: &str ) -> BaseUrl {
+    BaseUrl::try_from( url ).ok( ).unwrap( )
+}
+
+#[test]
+fn test_cache_origins( ) {
+    let cache = RobotsCache::new( MemoryFetcher::new( ) );
+    cache.fetcher( ).respond( "https://example.web/robots.txt", 200, b"User-agent: *\nDisallow: /private\n" )
+        .respond( "http://example.web/robots.txt", 200, b"User-agent: *\nDisallow: /\n" )
+        .respond( "https://example.web:8443/robots.txt", 404, b"" );
+
+    assert!( !cache.is_allowed( &url( "https://example.web/private" ), "bot" ) );
+    assert!( cache.is_allowed( &url( "https://example.web/public?q=1" ), "bot" ) );
+    assert!( !cache.is_allowed( &url( "http://example.web/public" ), "bot" ) );
+    assert!( cache.is_allowed( &url( "https://example.web:8443/private" ), "bot" ) );
+    assert!( cache.is_allowed( &url( "https://example.web:443/public" ), "bot" ) );
+
+    assert_eq!( cache.len( ), 3 );
+    assert_eq!( cache.fetcher( ).requested( ), vec![
+        String::from( "https://example.web/robots.txt" ),
+        String::from( "http://example.web/robots.txt" ),
+        String::from( "https://example.web:8443/robots.txt" ),
+    ] );
+
+    let legacy = ParseOptions::default( ).policy( MatchingPolicy::legacy( ) );
+    let options = RobotsCache::new( MemoryFetcher::new( ) ).options( legacy );
+    assert_eq!( options.get( &url( "https://unknown.web/" ) ).parse_options( ), legacy );
+
+    assert!( cache.remove( &url( "http://example.web/" ) ).is_some( ) );
+    assert!( cache.cached( &url( "http://example.web/" ) ).is_none( ) );
+    cache.clear( );
+    assert!( cache.is_empty( ) );
+}
+
+#[test]
+fn test_cache_expiry( ) {
+    let cache = RobotsCache::new( MemoryFetcher::new( ) );
+    cache.fetcher( ).respond( "https://fresh.web/robots.txt", 200, b"" )
+        .respond( "https://aged.web/robots.txt", 200, b"" )
+        .header( "https://aged.web/robots.txt", "Cache-Control", "public, max-age=0" )
+        .header( "https://aged.web/robots.txt", "Expires", "Fri, 01 Jan 2100 00:00:00 GMT" )
+        .respond( "https://expired.web/robots.txt", 200, b"" )
+        .header( "https://expired.web/robots.txt", "Expires", "Thu, 01 Jan 1970 00:00:00 GMT" )
+        .respond( "https://later.web/robots.txt", 200, b"" )
+        .header( "https://later.web/robots.txt", "Expires", "Fri, 01 Jan 2100 00:00:00 GMT" )
+        .respond( "https://unreadable.web/robots.txt", 200, b"" )
+        .header( "https://unreadable.web/robots.txt", "Expires", "0" );
+
+    for _ in 0..2 {
+        for site in &[ "fresh", "aged", "expired", "later", "unreadable" ] {
+            cache.get( &url( &format!( "https://{}.web/", site ) ) );
+        }
+    }
+
+    let requested = cache.fetcher( ).requested( );
+    let count = | site: &str |{
+        let robots_url = format!( "https://{}.web/robots.txt", site );
+        requested.iter( ).filter( | r |{ **r == robots_url } ).count( )
+    };
+    assert_eq!( count( "fresh" ), 1 );
+    assert_eq!( count( "aged" ), 2 );
+    assert_eq!( count( "expired" ), 2 );
+    assert_eq!( count( "later" ), 1 );
+    assert_eq!( count( "unreadable" ), 2 );
+
+    let short = RobotsCache::new( MemoryFetcher::new( ) ).expiry( Duration::from_secs( 0 ) );
+    short.fetcher( ).respond( "https://fresh.web/robots.txt", 200, b"" );
+    short.get( &url( "https://fresh.web/" ) );
+    short.get( &url( "https://fresh.web/" ) );
+    assert_eq!( short.fetcher( ).requested( ).len( ), 2 );
+}
+
+#[test]
+fn test_cache_stale( ) {
+    let robots_url = "https://example.web/robots.txt";
+    let cache = RobotsCache::new( MemoryFetcher::new( ) ).retry( Duration::from_secs( 0 ) );
+    cache.fetcher( ).respond( robots_url, 200, b"User-agent: *\nDisallow: /private\n" )
+        .header( robots_url, "Cache-Control", "max-age=0" );
+
+    let public = url( "https://example.web/public" );
+    let private = url( "https://example.web/private" );
+    assert!( cache.is_allowed( &public, "bot" ) );
+
+    //NOTE: the old document keeps being used while the server is failing or unreachable
+    cache.fetcher( ).respond( robots_url, 503, b"Try later" );
+    assert!( cache.is_allowed( &public, "bot" ) );
+    assert!( !cache.is_allowed( &private, "bot" ) );
+    cache.fetcher( ).forget( robots_url );
+    assert!( cache.is_allowed( &public, "bot" ) );
+    assert_eq!( cache.fetcher( ).requested( ).len( ), 4 );
+
+    cache.fetcher( ).respond( robots_url, 404, b"" );
+    assert!( cache.is_allowed( &private, "bot" ) );
+
+    //NOTE: with nothing to fall back on a failing server disallows everything
+    cache.fetcher( ).respond( "https://broken.web/robots.txt", 500, b"" );
+    assert!( !cache.is_allowed( &url( "https://broken.web/public" ), "bot" ) );
+
+    let waiting = RobotsCache::new( MemoryFetcher::new( ) );
+    waiting.fetcher( ).respond( robots_url, 200, b"" ).header( robots_url, "Cache-Control", "max-age=0" );
+    waiting.get( &public );
+    waiting.fetcher( ).respond( robots_url, 503, b"" );
+    waiting.get( &public );
+    waiting.get( &public );
+    assert_eq!( waiting.fetcher( ).requested( ).len( ), 2 );
+}
+
+#[test]
+fn test_cache_capacity( ) {
+    let cache = RobotsCache::new( MemoryFetcher::new( ) ).capacity( 2 );
+    for site in &[ "a", "b", "c" ] {
+        cache.fetcher( ).respond( &format!( "https://{}.web/robots.txt", site ), 200, b"" );
+    }
+
+    cache.get( &url( "https://a.web/" ) );
+    cache.get( &url( "https://b.web/" ) );
+    cache.get( &url( "https://a.web/" ) );
+    cache.get( &url( "https://c.web/" ) );
+
+    assert_eq!( cache.len( ), 2 );
+    assert!( cache.cached( &url( "https://a.web/" ) ).is_some( ) );
+    assert!( cache.cached( &url( "https://b.web/" ) ).is_none( ) );
+    assert!( cache.cached( &url( "https://c.web/" ) ).is_some( ) );
+}
+
+#[test]
+fn test_cache_hostile_headers( ) {
+    let forever = Duration::from_secs( u64::MAX );
+    let cache = RobotsCache::new( MemoryFetcher::new( ) ).expiry( forever );
+    cache.fetcher( ).respond( "https://age.web/robots.txt", 200, b"" )
+        .header( "https://age.web/robots.txt", "Cache-Control", "max-age=99999999999999999999999" )
+        .respond( "https://far.web/robots.txt", 200, b"" )
+        .header( "https://far.web/robots.txt", "Expires",
+                 "Fri, 01 Jan 18446744073709551615 00:00:00 GMT" )
+        .respond( "https://later.web/robots.txt", 200, b"" )
+        .header( "https://later.web/robots.txt", "Expires", "Sat, 01 Jan 600000000000 00:00:00 GMT" )
+        .respond( "https://plain.web/robots.txt", 200, b"" );
+
+    //NOTE: overly long lifetimes are cut short rather than overflowing, unreadable dates expire at once
+    for site in &[ "age", "plain", "far", "later" ] {
+        let page = url( &format!( "https://{}.web/", site ) );
+        cache.get( &page );
+        cache.get( &page );
+    }
+    assert_eq!( cache.fetcher( ).requested( ).len( ), 6 );
+}
+
+/// A MemoryFetcher which panics part way through a request while it's told to
+struct Panicking {
+    fetcher: MemoryFetcher,
+    panics: AtomicBool,
+}
+
+impl RobotsFetcher for Panicking {
+    fn fetch( &self, url: &BaseUrl, limit: usize ) -> Result< Fetched, RobotsError > {
+        if self.panics.load( Ordering::SeqCst ) {
+            panic!( "the connection was lost" );
+        }
+        self.fetcher.fetch( url, limit )
+    }
+}
+
+#[test]
+fn test_cache_panicking_refresh( ) {
+    let robots_url = "https://example.web/robots.txt";
+    let page = url( "https://example.web/page" );
+    let cache = RobotsCache::new( Panicking{
+        fetcher: MemoryFetcher::new( ),
+        panics: AtomicBool::new( false ),
+    } );
+    cache.fetcher( ).fetcher.respond( robots_url, 200, b"" )
+        .header( robots_url, "Cache-Control", "max-age=0" );
+    assert!( cache.is_allowed( &page, "bot" ) );
+
+    cache.fetcher( ).panics.store( true, Ordering::SeqCst );
+    assert!( panic::catch_unwind( AssertUnwindSafe( ||{ cache.get( &page ) } ) ).is_err( ) );
+
+    //NOTE: the refresh which panicked doesn't stop the next one from being made
+    cache.fetcher( ).panics.store( false, Ordering::SeqCst );
+    cache.fetcher( ).fetcher.respond( robots_url, 200, b"User-agent: *\nDisallow: /\n" );
+    assert!( !cache.is_allowed( &page, "bot" ) );
+}
@@ -49,3 +49,135 @@ pub static ROBOTS_WILD: &'static str =
      Sitemap: https://www.example.web/sitemaps/sitemap-section.xml \n\
      Sitemap: https://www.example.web/sitemaps/foo/index.xml \n";
 
+
+//A robots.txt file with Crawl-delay directives, some of which are unusable
+pub static ROBOTS_DELAYS: &'static str =
+    "User-agent: * \n\
+     Crawl-delay: 10 \n\
+     Disallow: /private/ \n\
+     \n\
+     User-agent: fastbot \n\
+     Crawl-delay: 0.5 \n\
+     \n\
+     User-agent: badbot \n\
+     Crawl-delay: -3 \n\
+     Crawl-delay: soon \n\
+     Crawl-delay: 1e30 \n";
+//Agents without a usable delay of their own should fall back on the wildcard section's 10 seconds
+
+//A robots.txt file asking for a slower pace at certain times of the day
+pub static ROBOTS_POLITENESS: &'static str =
+    "User-agent: * \n\
+     Crawl-delay: 2 \n\
+     Request-rate: 1/10s 0800-1700 \n\
+     Request-rate: 1/1s \n\
+     Visit-time: 2200-0600 \n\
+     \n\
+     User-agent: nightowl \n\
+     Visit-time: 0000-0400 \n\
+     Request-rate: often \n";
+
+//A Yandex-era robots.txt file which names its preferred mirror from inside of a User-agent section
+pub static ROBOTS_HOST: &'static str =
+    "User-agent: Yandex \n\
+     Disallow: /admin/ \n\
+     Host: www.example.com:8080 \n\
+     \n\
+     Host: mirror.example.com \n\
+     Host: not a host \n";
+
+//A robots.txt file whose answers depend on how the matching Rules are ranked
+pub static ROBOTS_PRECEDENCE: &'static str =
+    "User-agent: * \n\
+     Disallow: /page \n\
+     Allow: /page.html \n\
+     Disallow: /fish*.php \n\
+     Disallow: /tie \n\
+     Allow: /tie \n";
+//Ranking by path segments only ever matches whole segments, while RFC 9309 matches from the start of the
+// path and prefers the longest pattern, with Allow winning a tie
+
+//A robots.txt file using the '$' end-of-path anchor
+pub static ROBOTS_ANCHORS: &'static str =
+    "User-agent: * \n\
+     Disallow: /*.pdf$ \n\
+     Disallow: /$ \n";
+//PDF files and the root page itself are off limits, but nothing else is
+
+//A robots.txt file with rules which can only match the query string of a url
+pub static ROBOTS_QUERIES: &'static str =
+    "User-agent: * \n\
+     Disallow: /*?sessionid= \n\
+     Disallow: /search?q= \n\
+     Disallow: /private \n\
+     Disallow: /*tracking \n";
+
+//A robots.txt file with an empty Disallow, which disallows nothing
+pub static ROBOTS_EMPTY: &'static str =
+    "User-agent: bot \n\
+     Disallow: \n\
+     \n\
+     User-agent: * \n\
+     Disallow: /private \n";
+
+//A robots.txt file whose paths are spelled with a mix of escapes and raw characters
+pub static ROBOTS_ENCODING: &'static str =
+    "User-agent: * \n\
+     Disallow: /caf%C3%A9 \n\
+     Disallow: /%7Eadmin/ \n\
+     Disallow: /menü \n";
+
+//A robots.txt file naming agents the way they're written in full user agent strings
+pub static ROBOTS_AGENTS: &'static str =
+    "User-agent: * \n\
+     Disallow: / \n\
+     \n\
+     User-agent: OurBot \n\
+     Allow: / \n\
+     \n\
+     User-agent: a \n\
+     Allow: / \n\
+     \n\
+     User-agent: Mozilla \n\
+     Disallow: / \n";
+//Only our own bot and the agent named a are welcome, when matching by product token "aardvark" and
+// "OurBotanist" are strangers but when matching by prefix they're let in. Browsers are named only to
+// be turned away, which shouldn't catch our bot when it writes its user agent the way browsers do.
+
+//A robots.txt file which names the same agent in two separate sections
+pub static ROBOTS_DUPLICATES: &'static str =
+    "User-agent: Googlebot \n\
+     Disallow: /private \n\
+     \n\
+     User-agent: * \n\
+     Disallow: / \n\
+     \n\
+     User-agent: googlebot \n\
+     Disallow: /drafts \n\
+     Crawl-delay: 2 \n";
+//Both Googlebot sections are one and the same, /private and /drafts are both off limits and the delay
+// from the second section applies
+
+//A robots.txt file which leaves out the empty lines between sections and puts one inside of a section
+pub static ROBOTS_UNSPACED: &'static str =
+    "User-agent: * \n\
+     Disallow: /private \n\
+     User-agent: Bot \n\
+     Disallow: /bot-free \n\
+     \n\
+     Disallow: /also-bot-free \n";
+//Read by empty lines Bot is ignored and /also-bot-free is an orphan, read the way RFC 9309 asks Bot has
+// a section of its own holding both of its rules
+
+//A robots.txt file written with the misspellings and separators found in the wild
+pub static ROBOTS_MISSPELT: &'static str =
+    "Sitemap http://www.example.web/sitemap.xml \n\
+     \n\
+     useragent: * \n\
+     Dissallow: /private \n\
+     Disalow /drafts \n\
+     \n\
+     User agent: Bot \n\
+     Disallow : /bot-free \n\
+     : nameless \n";
+//Every directive here should still be read, with each fix-up recorded as a Misspelling
@@ -0,0 +1,207 @@
+extern crate rs_pider_robots;
+extern crate base_url;
+
+use rs_pider_robots::*;
+
+use base_url::BaseUrl;
+use base_url::TryFrom;
+
+/***********
+ * Fetching with reqwest from a local server
+ ******/
+
+#[cfg( feature="fetch" )]
+mod reqwest_fetching {
+
+    use std::io::{ Read, Write };
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Serves canned responses from a local port, handing each request's path to respond( ) for the
+    /// status line, any extra headers and the body
+    fn serve< F >( respond: F ) -> u16
+        where F: Fn( &str ) -> ( &'static str, String, String ) + Send + 'static {
+
+        let listener = TcpListener::bind( "127.0.0.1:0" ).unwrap( );
+        let port = listener.local_addr( ).unwrap( ).port( );
+
+        thread::spawn( move ||{
+            for stream in listener.incoming( ) {
+                let mut stream = match stream { Ok( s ) => s, Err( _e ) => { continue; } };
+
+                let mut request = Vec::new( );
+                let mut buffer = [ 0; 1024 ];
+                while !String::from_utf8_lossy( &request ).contains( "\r\n\r\n" ) {
+                    match stream.read( &mut buffer ) {
+                        Ok( 0 ) | Err( _ ) => { break; }
+                        Ok( n ) => { request.extend_from_slice( &buffer[ ..n ] ); }
+                    }
+                }
+                let request = String::from_utf8_lossy( &request ).to_string( );
+                let path = request.split_whitespace( ).nth( 1 ).unwrap_or( "/" ).to_string( );
+
+                let ( status, headers, body ) = respond( &path );
+                let _ = write!( stream,
+                                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+                                status, body.len( ), headers, body );
+            }
+        } );
+
+        port
+    }
+
+    fn page( port: u16, path: &str ) -> BaseUrl {
+        BaseUrl::try_from( format!( "http://127.0.0.1:{}{}", port, path ).as_str( ) ).ok( ).unwrap( )
+    }
+
+    #[test]
+    fn test_fetch_found( ) {
+        let port = serve( | path |{
+            if path == "/robots.txt" {
+                ( "200 OK", String::new( ), String::from( "User-agent: *\nDisallow: /private\n" ) )
+            } else {
+                ( "404 Not Found", String::new( ), String::new( ) )
+            }
+        } );
+
+        let robots = fetch_robots( &page( port, "/some/page?query" ) );
+        assert!( !robots.is_allowed( &page( port, "/private" ), "bot" ) );
+        assert!( robots.is_allowed( &page( port, "/public" ), "bot" ) );
+    }
+
+    #[test]
+    fn test_fetch_status( ) {
+        let missing = serve( | _path |{ ( "404 Not Found", String::new( ), String::new( ) ) } );
+        let failing = serve( | _path |{ ( "503 Service Unavailable", String::new( ), String::new( ) ) } );
+
+        assert!( fetch_robots( &page( missing, "/" ) )
+                 .is_allowed( &page( missing, "/private" ), "bot" ) );
+        assert!( !fetch_robots( &page( failing, "/" ) )
+                 .is_allowed( &page( failing, "/public" ), "bot" ) );
+    }
+
+    #[test]
+    fn test_fetch_redirects( ) {
+        //NOTE: /robots.txt redirects through /hop/1 .. /hop/n before the document itself
+        let hops = | n: usize |{
+            move | path: &str |{
+                let hop = if path == "/robots.txt" { 0 } else {
+                    path.trim_start_matches( "/hop/" ).parse::< usize >( ).unwrap_or( n )
+                };
+                if hop < n {
+                    let location = format!( "Location: /hop/{}\r\n", hop + 1 );
+                    ( "301 Moved Permanently", location, String::new( ) )
+                } else {
+                    ( "200 OK", String::new( ), String::from( "User-agent: *\nDisallow: /\n" ) )
+                }
+            }
+        };
+
+        let near = serve( hops( MAX_REDIRECTS ) );
+        let far = serve( hops( MAX_REDIRECTS + 1 ) );
+
+        assert!( !fetch_robots( &page( near, "/" ) ).is_allowed( &page( near, "/page" ), "bot" ) );
+        assert!( fetch_robots( &page( far, "/" ) ).is_allowed( &page( far, "/page" ), "bot" ) );
+    }
+
+    #[test]
+    fn test_fetch_unreachable( ) {
+        //NOTE: the listener stays bound so no one else can take the port, but every connection is closed
+        // before anything is answered
+        let listener = TcpListener::bind( "127.0.0.1:0" ).unwrap( );
+        let port = listener.local_addr( ).unwrap( ).port( );
+        thread::spawn( move ||{
+            for stream in listener.incoming( ) {
+                drop( stream );
+            }
+        } );
+
+        let fetcher = ReqwestFetcher::new( Duration::from_secs( 2 ) ).unwrap( );
+        let robots = fetch_robots_with( &fetcher, &page( port, "/" ), &ParseOptions::default( ) );
+        assert!( !robots.is_allowed( &page( port, "/page" ), "bot" ) );
+    }
+}
+
+/***********
+ * Fetching from memory
+ ******/
+
+fn url( url: &str ) -> BaseUrl {
+    BaseUrl::try_from( url ).ok( ).unwrap( )
+}
+
+#[test]
+fn test_memory_fetcher( ) {
+    let fetcher = MemoryFetcher::new( );
+    fetcher.respond( "https://example.web/robots.txt", 200, b"User-agent: *\nDisallow: /private\n" );
+    let options = ParseOptions::default( );
+
+    let robots = fetch_robots_with( &fetcher, &url( "https://example.web/a/page" ), &options );
+    assert!( !robots.is_allowed( &url( "https://example.web/private" ), "bot" ) );
+    assert!( robots.is_allowed( &url( "https://example.web/public" ), "bot" ) );
+    assert_eq!( fetcher.requested( ), vec![ String::from( "https://example.web/robots.txt" ) ] );
+
+    let elsewhere = fetch_robots_with( &fetcher, &url( "https://other.web/" ), &options );
+    assert!( !elsewhere.is_allowed( &url( "https://other.web/public" ), "bot" ) );
+
+    let legacy = ParseOptions::default( ).policy( MatchingPolicy::legacy( ) );
+    let unreached = fetch_robots_with( &fetcher, &url( "https://other.web/" ), &legacy );
+    assert_eq!( unreached.parse_options( ), legacy );
+}
+
+#[test]
+fn test_memory_redirects( ) {
+    let fetcher = MemoryFetcher::new( );
+    fetcher.redirect( "https://example.web/robots.txt", "https://www.example.web/robots.txt" )
+        .redirect( "https://www.example.web/robots.txt", "/hop/1" );
+    for hop in 1..MAX_REDIRECTS {
+        fetcher.redirect( &format!( "https://www.example.web/hop/{}", hop ), &format!( "{}", hop + 1 ) );
+    }
+    fetcher.respond( &format!( "https://www.example.web/hop/{}", MAX_REDIRECTS ), 200, b"Disallow: /\n" );
+
+    let page = url( "https://example.web/page" );
+    let options = ParseOptions::default( );
+
+    //NOTE: the document is one redirect too far away, so it's taken to be unavailable
+    let robots_url = url( "https://example.web/robots.txt" );
+    let fetched = fetch_following( &fetcher, &robots_url, &options ).unwrap( );
+    assert_eq!( fetched.status, 301 );
+    assert!( fetch_robots_with( &fetcher, &page, &options ).is_allowed( &page, "bot" ) );
+
+    fetcher.respond( &format!( "https://www.example.web/hop/{}", MAX_REDIRECTS - 1 ), 200,
+                     b"User-agent: *\nDisallow: /\n" );
+    assert!( !fetch_robots_with( &fetcher, &page, &options ).is_allowed( &page, "bot" ) );
+}
+
+#[test]
+fn test_memory_locations( ) {
+    let fetcher = MemoryFetcher::new( );
+    fetcher.redirect( "https://example.web/robots.txt", "/go?to=http://elsewhere.web/" )
+        .redirect( "https://example.web/go?to=http://elsewhere.web/", "?to=here" )
+        .redirect( "https://example.web/go?to=here", "./a/../b/./robots.txt" )
+        .respond( "https://example.web/b/robots.txt", 200, b"User-agent: *\nDisallow: /\n" );
+
+    let page = url( "https://example.web/page" );
+    let robots = fetch_robots_with( &fetcher, &page, &ParseOptions::default( ) );
+    assert!( !robots.is_allowed( &page, "bot" ) );
+
+    let last = fetcher.requested( ).pop( );
+    assert_eq!( last, Some( String::from( "https://example.web/b/robots.txt" ) ) );
+}
+
+#[test]
+fn test_memory_limit( ) {
+    let fetcher = MemoryFetcher::new( );
+    fetcher.respond( "https://example.web/robots.txt", 200,
+                     b"User-agent: *\nDisallow: /private\nDisallow: /everything-else\n" );
+
+    let options = ParseOptions::default( ).max_bytes( 33 );
+    let robots = fetch_robots_with( &fetcher, &url( "https://example.web/" ), &options );
+
+    assert!( !robots.is_allowed( &url( "https://example.web/private" ), "bot" ) );
+    assert!( robots.is_allowed( &url( "https://example.web/everything-else" ), "bot" ) );
+    assert_eq!( robots.get_toplevel_anomalies( ).last( ), Some( &&Anomaly::Truncated( 33 ) ) );
+}
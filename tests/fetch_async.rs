@@ -0,0 +1,87 @@
+#![cfg( feature="async" )]
+
+extern crate rs_pider_robots;
+extern crate base_url;
+extern crate futures;
+
+use rs_pider_robots::*;
+
+use base_url::BaseUrl;
+use base_url::TryFrom;
+
+use futures::executor::block_on;
+
+fn url( url: &str ) -> BaseUrl {
+    BaseUrl::try_from( url ).ok( ).unwrap( )
+}
+
+#[test]
+fn test_async_statuses( ) {
+    let fetcher = MemoryFetcher::new( );
+    fetcher.respond( "https://found.web/robots.txt", 200, b"User-agent: *\nDisallow: /private\n" )
+        .respond( "https://missing.web/robots.txt", 404, b"Not here" )
+        .respond( "https://broken.web/robots.txt", 503, b"Try later" );
+    let options = ParseOptions::default( );
+
+    let found = block_on( fetch_robots_async( &fetcher, &url( "https://found.web/a/page" ), &options ) );
+    assert!( !found.is_allowed( &url( "https://found.web/private" ), "bot" ) );
+    assert!( found.is_allowed( &url( "https://found.web/public" ), "bot" ) );
+
+    let missing = block_on( fetch_robots_async( &fetcher, &url( "https://missing.web/" ), &options ) );
+    assert!( missing.is_allowed( &url( "https://missing.web/private" ), "bot" ) );
+
+    let broken = block_on( fetch_robots_async( &fetcher, &url( "https://broken.web/" ), &options ) );
+    assert!( !broken.is_allowed( &url( "https://broken.web/public" ), "bot" ) );
+
+    let unreachable = block_on( fetch_robots_async( &fetcher, &url( "https://nowhere.web/" ), &options ) );
+    assert!( !unreachable.is_allowed( &url( "https://nowhere.web/public" ), "bot" ) );
+
+    assert_eq!( fetcher.requested( ), vec![ String::from( "https://found.web/robots.txt" ),
+                                            String::from( "https://missing.web/robots.txt" ),
+                                            String::from( "https://broken.web/robots.txt" ),
+                                            String::from( "https://nowhere.web/robots.txt" ) ] );
+}
+
+#[test]
+fn test_async_redirects( ) {
+    let fetcher = MemoryFetcher::new( );
+    fetcher.redirect( "https://example.web/robots.txt", "https://www.example.web/robots.txt" )
+        .redirect( "https://www.example.web/robots.txt", "/hop/1" );
+    for hop in 1..MAX_REDIRECTS {
+        fetcher.redirect( &format!( "https://www.example.web/hop/{}", hop ), &format!( "{}", hop + 1 ) );
+    }
+    fetcher.respond( &format!( "https://www.example.web/hop/{}", MAX_REDIRECTS ), 200, b"Disallow: /\n" );
+
+    let page = url( "https://example.web/page" );
+    let options = ParseOptions::default( );
+
+    //NOTE: the document is one redirect too far away, so it's taken to be unavailable
+    let robots_url = url( "https://example.web/robots.txt" );
+    let fetched = block_on( fetch_following_async( &fetcher, &robots_url, &options ) ).unwrap( );
+    assert_eq!( fetched.status, 301 );
+    assert!( block_on( fetch_robots_async( &fetcher, &page, &options ) ).is_allowed( &page, "bot" ) );
+
+    fetcher.respond( &format!( "https://www.example.web/hop/{}", MAX_REDIRECTS - 1 ), 200,
+                     b"User-agent: *\nDisallow: /\n" );
+    assert!( !block_on( fetch_robots_async( &fetcher, &page, &options ) ).is_allowed( &page, "bot" ) );
+}
+
+#[test]
+fn test_async_limit( ) {
+    let mut body = String::from( "User-agent: *\nDisallow: /private\n" );
+    while body.len( ) < 2048 {
+        body.push_str( "Disallow: /everything-else\n" );
+    }
+    let fetcher = MemoryFetcher::new( );
+    fetcher.respond( "https://example.web/robots.txt", 200, body.as_bytes( ) );
+
+    let options = ParseOptions::default( ).max_bytes( 33 );
+    let robots_url = url( "https://example.web/robots.txt" );
+    let fetched = block_on( fetch_following_async( &fetcher, &robots_url, &options ) ).unwrap( );
+    assert_eq!( fetched.body.len( ), 35 );
+
+    let robots = block_on( fetch_robots_async( &fetcher, &url( "https://example.web/" ), &options ) );
+    assert!( !robots.is_allowed( &url( "https://example.web/private" ), "bot" ) );
+    assert!( robots.is_allowed( &url( "https://example.web/everything-else" ), "bot" ) );
+    assert_eq!( robots.get_toplevel_anomalies( ).last( ), Some( &&Anomaly::Truncated( 33 ) ) );
+}
@@ -2,13 +2,19 @@
 extern crate rs_pider_robots;
 extern crate base_url;
 
+use std::time::Duration;
+
 use rs_pider_robots::*;
 
 use base_url::BaseUrl;
 use base_url::TryFrom;
 
 mod data;
-use data::{ ROBOTS_SIMPLE, ROBOTS_OVERLAPPING, ROBOTS_SITEMAPS, ROBOTS_WILD };
+use data::{ ROBOTS_SIMPLE, ROBOTS_OVERLAPPING, ROBOTS_SITEMAPS, ROBOTS_WILD,
+            ROBOTS_DELAYS, ROBOTS_POLITENESS, ROBOTS_HOST,
+            ROBOTS_PRECEDENCE, ROBOTS_ANCHORS, ROBOTS_QUERIES,
+            ROBOTS_ENCODING, ROBOTS_AGENTS, ROBOTS_DUPLICATES,
+            ROBOTS_UNSPACED, ROBOTS_MISSPELT, ROBOTS_EMPTY };
 
 #[test]
 fn test_simple_robots( ) {
@@ -27,7 +33,8 @@ fn test_overlapping_robots( ) {
 
     let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
 
-    let overlapping = RobotsParser::from_stringable( ROBOTS_OVERLAPPING, host );
+    let legacy = ParseOptions::default( ).policy( MatchingPolicy::legacy( ) );
+    let overlapping = RobotsParser::parse_with( host, ROBOTS_OVERLAPPING, &legacy );
 
     let url1 = BaseUrl::try_from( "https://example.com/foo" ).ok( ).unwrap( );
     let url2 = BaseUrl::try_from( "https://example.com/foo/bar" ).ok( ).unwrap( );
@@ -50,3 +57,677 @@ fn test_sitemaps_robots( ) {
 
     assert!( sitemaps.get_sitemaps( ).len( ) == 3 );
 }
+
+#[test]
+fn test_crawl_delay_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+
+    let delays = RobotsParser::from_stringable( ROBOTS_DELAYS, host.clone( ) );
+
+    assert_eq!( delays.crawl_delay( "somebot" ), Some( Duration::from_secs( 10 ) ) );
+    assert_eq!( delays.crawl_delay( "fastbot" ), Some( Duration::from_millis( 500 ) ) );
+    assert_eq!( delays.crawl_delay( "badbot" ), Some( Duration::from_secs( 10 ) ) );
+
+    let bad_arguments = delays.get_agent_anomalies( "badbot" ).into_iter( ).filter(
+        | anomaly |{ match anomaly { Anomaly::BadArgument( _, _ ) => true, _ => false } }
+    ).count( );
+    assert_eq!( bad_arguments, 3 );
+
+    //NOTE: the first Crawl-delay in a section wins, the same as between merged sections
+    let repeated = RobotsParser::from_stringable( "User-agent: bot\nCrawl-delay: 1\nCrawl-delay: 5\n",
+                                                  host.clone( ) );
+    assert_eq!( repeated.crawl_delay( "bot" ), Some( Duration::from_secs( 1 ) ) );
+    assert!( repeated.get_agent_anomalies( "bot" )
+             .contains( &&Anomaly::ExtraCrawlDelay( String::from( "5" ) ) ) );
+}
+
+#[test]
+fn test_politeness_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+
+    let politeness = RobotsParser::from_stringable( ROBOTS_POLITENESS, host );
+
+    let somebot = politeness.politeness( "somebot" );
+    assert_eq!( somebot.request_rates.len( ), 2 );
+    assert_eq!( somebot.interval_at( 12, 0 ), Some( Duration::from_secs( 10 ) ) );
+    assert_eq!( somebot.interval_at( 20, 0 ), Some( Duration::from_secs( 2 ) ) );
+    assert!( somebot.may_visit_at( 23, 30 ) );
+    assert!( !somebot.may_visit_at( 12, 0 ) );
+
+    let nightowl = politeness.politeness( "nightowl" );
+    assert_eq!( nightowl.crawl_delay, Some( Duration::from_secs( 2 ) ) );
+    assert_eq!( nightowl.request_rates, somebot.request_rates );
+    assert_eq!( nightowl.visit_times, vec![ VisitTime{ start: ( 0, 0 ), end: ( 4, 0 ) } ] );
+}
+
+#[test]
+fn test_host_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+    let mirror = BaseUrl::try_from( "https://www.example.com:8080/" ).ok( ).unwrap( );
+
+    let hosted = RobotsParser::from_stringable( ROBOTS_HOST, host.clone( ) );
+
+    assert_eq!( hosted.preferred_host( ), Some( mirror ) );
+
+    let toplevel: Vec< &Anomaly > = hosted.get_toplevel_anomalies( );
+    assert!( toplevel.contains( &&Anomaly::ExtraHost( String::from( "mirror.example.com" ) ) ) );
+    assert!( toplevel.contains( &&Anomaly::BadArgument( String::from( "Host" ),
+                                                        String::from( "not a host" ) ) ) );
+
+    let bracketed = RobotsParser::from_stringable( "Host: [2001:db8::1]:8080\n", host.clone( ) );
+    let mirror = BaseUrl::try_from( "https://[2001:db8::1]:8080/" ).ok( ).unwrap( );
+    assert_eq!( bracketed.preferred_host( ), Some( mirror ) );
+
+    let unclosed = RobotsParser::from_stringable( "Host: [2001:db8::1:8080\n", host );
+    assert_eq!( unclosed.preferred_host( ), None );
+}
+
+#[test]
+fn test_precedence_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+
+    let segments = RobotsParser::from_stringable( ROBOTS_PRECEDENCE, host.clone( ) );
+    let rfc9309 = ParseOptions::default( ).policy( MatchingPolicy::rfc9309( ) );
+    let longest = RobotsParser::parse_with( host, ROBOTS_PRECEDENCE, &rfc9309 );
+
+    let pages = BaseUrl::try_from( "https://example.com/pages" ).ok( ).unwrap( );
+    let page = BaseUrl::try_from( "https://example.com/page.html" ).ok( ).unwrap( );
+    let fish = BaseUrl::try_from( "https://example.com/fishheads/catfish.php" ).ok( ).unwrap( );
+    let tie = BaseUrl::try_from( "https://example.com/tie" ).ok( ).unwrap( );
+
+    assert!( segments.is_allowed( &pages, "bot" ) );
+    assert!( !longest.is_allowed( &pages, "bot" ) );
+
+    assert!( segments.is_allowed( &page, "bot" ) );
+    assert!( longest.is_allowed( &page, "bot" ) );
+
+    assert!( segments.is_allowed( &fish, "bot" ) );
+    assert!( !longest.is_allowed( &fish, "bot" ) );
+
+    assert!( !segments.is_allowed( &tie, "bot" ) );
+    assert!( longest.is_allowed( &tie, "bot" ) );
+}
+
+#[test]
+fn test_anchored_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+
+    let segments = RobotsParser::from_stringable( ROBOTS_ANCHORS, host.clone( ) );
+    let rfc9309 = ParseOptions::default( ).policy( MatchingPolicy::rfc9309( ) );
+    let longest = RobotsParser::parse_with( host, ROBOTS_ANCHORS, &rfc9309 );
+
+    let root = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+    let pdf = BaseUrl::try_from( "https://example.com/report.pdf" ).ok( ).unwrap( );
+    let deep_pdf = BaseUrl::try_from( "https://example.com/docs/report.pdf" ).ok( ).unwrap( );
+    let not_pdf = BaseUrl::try_from( "https://example.com/report.pdf.html" ).ok( ).unwrap( );
+
+    //NOTE: an anchored path is matched as a whole, even by path segments, so its asterisk crosses a '/'
+    for robots in vec![ &segments, &longest ] {
+        assert!( !robots.is_allowed( &root, "bot" ) );
+        assert!( !robots.is_allowed( &pdf, "bot" ) );
+        assert!( !robots.is_allowed( &deep_pdf, "bot" ) );
+        assert!( robots.is_allowed( &not_pdf, "bot" ) );
+    }
+}
+
+#[test]
+fn test_empty_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+
+    let segments = RobotsParser::from_stringable( ROBOTS_EMPTY, host.clone( ) );
+    let rfc9309 = ParseOptions::default( ).policy( MatchingPolicy::rfc9309( ) );
+    let longest = RobotsParser::parse_with( host, ROBOTS_EMPTY, &rfc9309 );
+
+    let page = BaseUrl::try_from( "https://example.com/page" ).ok( ).unwrap( );
+    let root = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+    let private = BaseUrl::try_from( "https://example.com/private/page" ).ok( ).unwrap( );
+
+    assert!( segments.is_allowed( &page, "bot" ) );
+    assert!( segments.is_allowed( &root, "bot" ) );
+    assert!( !segments.is_allowed( &private, "otherbot" ) );
+
+    assert!( longest.is_allowed( &page, "bot" ) );
+    assert!( longest.is_allowed( &root, "bot" ) );
+    assert!( !longest.is_allowed( &private, "otherbot" ) );
+    assert_eq!( longest.rules_for( "bot" )[ 0 ].value, Rule::Disallow( String::new( ) ) );
+}
+
+#[test]
+fn test_query_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+
+    let segments = RobotsParser::from_stringable( ROBOTS_QUERIES, host.clone( ) );
+    let rfc9309 = ParseOptions::default( ).policy( MatchingPolicy::rfc9309( ) );
+    let longest = RobotsParser::parse_with( host, ROBOTS_QUERIES, &rfc9309 );
+
+    let session = BaseUrl::try_from( "https://example.com/shop?sessionid=42" ).ok( ).unwrap( );
+    let search = BaseUrl::try_from( "https://example.com/search?q=robots" ).ok( ).unwrap( );
+    let plain_search = BaseUrl::try_from( "https://example.com/search" ).ok( ).unwrap( );
+    let other_query = BaseUrl::try_from( "https://example.com/shop?page=2" ).ok( ).unwrap( );
+
+    let private = BaseUrl::try_from( "https://example.com/private?x=1" ).ok( ).unwrap( );
+    let rust = BaseUrl::try_from( "https://example.com/search?q=rust" ).ok( ).unwrap( );
+    let tracked = BaseUrl::try_from( "https://example.com/shop?tracking=1" ).ok( ).unwrap( );
+
+    assert!( !segments.is_allowed( &session, "bot" ) );
+    assert!( !segments.is_allowed( &search, "bot" ) );
+    assert!( !segments.is_allowed( &rust, "bot" ) );
+    assert!( !segments.is_allowed( &private, "bot" ) );
+    assert!( segments.is_allowed( &plain_search, "bot" ) );
+    assert!( segments.is_allowed( &other_query, "bot" ) );
+    //NOTE: a path with no query of its own can still be found in the target's query
+    assert!( !segments.is_allowed( &tracked, "bot" ) );
+
+    assert!( !longest.is_allowed( &session, "bot" ) );
+    assert!( !longest.is_allowed( &search, "bot" ) );
+    assert!( longest.is_allowed( &plain_search, "bot" ) );
+    assert!( !longest.is_allowed( &rust, "bot" ) );
+    assert!( !longest.is_allowed( &private, "bot" ) );
+    assert!( longest.is_allowed( &other_query, "bot" ) );
+    assert!( !longest.is_allowed( &tracked, "bot" ) );
+}
+
+#[test]
+fn test_encoding_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+
+    let rfc9309 = ParseOptions::default( ).policy( MatchingPolicy::rfc9309( ) );
+    let encoded = RobotsParser::parse_with( host, ROBOTS_ENCODING, &rfc9309 );
+
+    let urls = vec![ "https://example.com/café",
+                     "https://example.com/caf%c3%a9",
+                     "https://example.com/~admin/users",
+                     "https://example.com/%7eadmin/users",
+                     "https://example.com/men%C3%BC" ];
+
+    for url in urls {
+        let url = BaseUrl::try_from( url ).ok( ).unwrap( );
+        assert!( !encoded.is_allowed( &url, "bot" ) );
+    }
+}
+
+#[test]
+fn test_check_origin( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+
+    let simple = RobotsParser::from_stringable( ROBOTS_SIMPLE, host );
+
+    let same = BaseUrl::try_from( "https://example.com/a/path/" ).ok( ).unwrap( );
+    let other_host = BaseUrl::try_from( "https://example.org/a/path/" ).ok( ).unwrap( );
+    let other_scheme = BaseUrl::try_from( "http://example.com/a/path/" ).ok( ).unwrap( );
+    let other_port = BaseUrl::try_from( "https://example.com:8443/a/path/" ).ok( ).unwrap( );
+
+    assert_eq!( simple.check( &same, "bot" ), Ok( Verdict::Disallowed ) );
+    assert_eq!( simple.check( &other_host, "bot" ),
+                Err( RobotsError::HostMismatch( String::from( "example.com" ),
+                                                String::from( "example.org" ) ) ) );
+    assert_eq!( simple.check( &other_scheme, "bot" ),
+                Err( RobotsError::SchemeMismatch( String::from( "https" ), String::from( "http" ) ) ) );
+    assert_eq!( simple.check( &other_port, "bot" ),
+                Err( RobotsError::PortMismatch( Some( 443 ), Some( 8443 ) ) ) );
+
+    assert!( !simple.is_allowed( &other_host, "bot" ) );
+    assert!( !simple.is_allowed( &other_scheme, "bot" ) );
+    assert!( !simple.is_allowed( &other_port, "bot" ) );
+}
+
+#[test]
+fn test_explain_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+
+    let overlapping = RobotsParser::from_stringable( ROBOTS_OVERLAPPING, host );
+
+    let url = BaseUrl::try_from( "https://example.com/foo/bar" ).ok( ).unwrap( );
+    let free = BaseUrl::try_from( "https://example.com/elsewhere" ).ok( ).unwrap( );
+
+    let explained = overlapping.explain( &url, "Bot" ).unwrap( );
+    assert_eq!( explained.verdict, Verdict::Allowed );
+    let rule = explained.rule.clone( ).unwrap( );
+    assert_eq!( rule.value, Rule::Allow( String::from( "/foo/bar" ) ) );
+    assert_eq!( rule.span.line, 5 );
+    assert_eq!( explained.agents, vec![ String::from( "Bot" ) ] );
+    let overruled: Vec< &Rule > = explained.overruled.iter( ).map( | rule |{ &rule.value } ).collect( );
+    assert_eq!( overruled, vec![ &Rule::Disallow( String::from( "/foo" ) ) ] );
+    assert!( explained.to_string( ).contains( "on line 5" ) );
+
+    let defaulted = overlapping.explain( &free, "Bot" ).unwrap( );
+    assert_eq!( defaulted.verdict, Verdict::Allowed );
+    assert!( defaulted.rule.is_none( ) );
+    assert!( defaulted.to_string( ).contains( "by default" ) );
+}
+
+#[test]
+fn test_spans_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+
+    let wild = RobotsParser::from_stringable( ROBOTS_WILD, host );
+
+    let sitemaps = wild.get_sitemaps_spanned( );
+    assert_eq!( sitemaps[ 0 ].span.line, 16 );
+    assert_eq!( sitemaps[ 1 ].span.line, 17 );
+    assert_eq!( &ROBOTS_WILD[ sitemaps[ 0 ].span.bytes( ) ].trim( ),
+                &"Sitemap: https://www.example.web/sitemaps/sitemap-section.xml" );
+
+    let comment = wild.get_all_anomalies_spanned( ).into_iter( ).find( | anomaly |{
+        match anomaly.value { Anomaly::Comment( _, _ ) => true, _ => false }
+    } ).unwrap( );
+    assert_eq!( comment.span.line, 8 );
+}
+
+#[test]
+fn test_groups_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+
+    let wild = RobotsParser::from_stringable( ROBOTS_WILD, host );
+
+    let names: Vec< &Vec< String > > = wild.groups( ).map( | group |{ group.names( ) } ).collect( );
+    assert_eq!( names, vec![ &vec![ String::from( "bender" ) ],
+                             &vec![ String::from( "008" ), String::from( "voltron" ) ],
+                             &vec![ String::from( "*" ) ] ] );
+
+    let wildcard = wild.groups( ).last( ).unwrap( );
+    assert_eq!( wildcard.rules( ).len( ), 5 );
+    assert_eq!( wildcard.span( ).line, 1 );
+
+    let rules: Vec< &Rule > = wild.rules_for( "bender" ).into_iter( )
+        .map( | rule |{ &rule.value } ).collect( );
+    assert_eq!( rules.len( ), 6 );
+    assert_eq!( rules[ 0 ], &Rule::Disallow( String::from( "/my_shiny_metal_ass" ) ) );
+}
+
+#[test]
+fn test_agents_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+
+    let tokens = RobotsParser::from_stringable( ROBOTS_AGENTS, host.clone( ) );
+    let legacy = ParseOptions::default( ).policy( MatchingPolicy::legacy( ) );
+    let prefixes = RobotsParser::parse_with( host.clone( ), ROBOTS_AGENTS, &legacy );
+
+    let page = BaseUrl::try_from( "https://example.web/page" ).ok( ).unwrap( );
+    let full = "Mozilla/5.0 (compatible; OurBot/2.1; +https://our.example/bot)";
+
+    assert!( tokens.is_allowed( &page, full ) );
+    assert!( tokens.is_allowed( &page, "ourbot" ) );
+    assert!( tokens.is_allowed( &page, "OurBot/2.1" ) );
+    assert!( !tokens.is_allowed( &page, "OurBotanist" ) );
+    assert!( !tokens.is_allowed( &page, "aardvark" ) );
+    assert!( tokens.is_allowed( &page, "A/1.0" ) );
+
+    let browser = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                   Chrome/120.0 Safari/537.36";
+    let embedded = "Mozilla/5.0 AppleWebKit/537.36 (KHTML, like Gecko; compatible; OurBot/2.1) \
+                    Chrome/120.0";
+    let rfc9309 = ParseOptions::default( ).policy( MatchingPolicy::rfc9309( ) );
+    let specific = RobotsParser::parse_with( host.clone( ), ROBOTS_AGENTS, &rfc9309 );
+
+    assert!( !tokens.is_allowed( &page, browser ) );
+    assert!( tokens.is_allowed( &page, embedded ) );
+    assert!( specific.is_allowed( &page, full ) );
+    assert!( specific.is_allowed( &page, embedded ) );
+    assert!( !specific.is_allowed( &page, browser ) );
+    assert!( !specific.is_allowed( &page, "like" ) );
+
+    assert!( !prefixes.is_allowed( &page, full ) );
+    assert!( prefixes.is_allowed( &page, "OurBotanist" ) );
+    assert!( prefixes.is_allowed( &page, "aardvark" ) );
+}
+
+#[test]
+fn test_duplicates_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+
+    let duplicates = RobotsParser::from_stringable( ROBOTS_DUPLICATES, host.clone( ) );
+
+    let private = BaseUrl::try_from( "https://example.web/private" ).ok( ).unwrap( );
+    let drafts = BaseUrl::try_from( "https://example.web/drafts" ).ok( ).unwrap( );
+
+    assert_eq!( duplicates.groups( ).count( ), 2 );
+    assert!( !duplicates.is_allowed( &private, "Googlebot" ) );
+    assert!( !duplicates.is_allowed( &drafts, "Googlebot" ) );
+    assert_eq!( duplicates.crawl_delay( "Googlebot" ), Some( Duration::from_secs( 2 ) ) );
+
+    let merged: Vec< &Spanned< Anomaly > > = duplicates.get_agent_anomalies_spanned( "Googlebot" )
+        .into_iter( )
+        .filter( | a |{ match a.value { Anomaly::MergedUserAgent( _ ) => true, _ => false } } ).collect( );
+    assert_eq!( merged.len( ), 1 );
+    assert_eq!( merged[ 0 ].span.line, 7 );
+
+    //NOTE: merged sections answer the same as though their rules had been written in a single section
+    let split = RobotsParser::from_stringable( "User-agent: bot\nAllow: /a\n\n\
+                                                User-agent: bot\nDisallow: /a/b\n", host.clone( ) );
+    let single = RobotsParser::from_stringable( "User-agent: bot\nAllow: /a\nDisallow: /a/b\n", host );
+    let nested = BaseUrl::try_from( "https://example.web/a/b" ).ok( ).unwrap( );
+    assert!( !single.is_allowed( &nested, "bot" ) );
+    assert!( !split.is_allowed( &nested, "bot" ) );
+    let values = | robots: &RobotsParser |{
+        robots.rules_for( "bot" ).into_iter( )
+            .map( | rule |{ rule.value.clone( ) } ).collect::< Vec< Rule > >( )
+    };
+    assert_eq!( values( &split ), values( &single ) );
+}
+
+#[test]
+fn test_group_selection_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.com/" ).ok( ).unwrap( );
+
+    let mut most_specific = MatchingPolicy::default( );
+    most_specific.group_selection = GroupSelection::MostSpecific;
+
+    let union = RobotsParser::from_stringable( ROBOTS_OVERLAPPING, host.clone( ) );
+    let selected = RobotsParser::parse_with( host.clone( ), ROBOTS_OVERLAPPING,
+                                             &ParseOptions::default( ).policy( most_specific ) );
+
+    let foo = BaseUrl::try_from( "https://example.com/foo" ).ok( ).unwrap( );
+    let baz = BaseUrl::try_from( "https://example.com/foo/bar/baz" ).ok( ).unwrap( );
+
+    //NOTE: under a union Bot is still bound by the wildcard section's Disallow: /foo, when only the most
+    // specific section is used the wildcard is ignored entirely
+    assert!( !union.is_allowed( &foo, "Bot" ) );
+    assert!( selected.is_allowed( &foo, "Bot" ) );
+    assert!( selected.is_allowed( &baz, "Bot" ) );
+
+    //Bot-1 is only named by its own section, which says nothing about /foo
+    assert!( selected.is_allowed( &foo, "Bot-1" ) );
+    assert!( !selected.is_allowed( &baz, "Bot-1" ) );
+
+    //Anyone unnamed falls back to the wildcard section either way
+    assert!( !union.is_allowed( &foo, "Stranger" ) );
+    assert!( !selected.is_allowed( &foo, "Stranger" ) );
+
+    assert_eq!( selected.rules_for( "Bot" ).len( ), 1 );
+    assert_eq!( union.rules_for( "Bot" ).len( ), 2 );
+
+    //NOTE: a name written with a version is the same agent as the name without one
+    let versioned = RobotsParser::parse_with( host, "User-agent: OurBot/1.0\nDisallow: /a\n\n\
+                                                     User-agent: OurBot\nDisallow: /b\n",
+                                              &ParseOptions::default( ).policy( most_specific ) );
+    assert_eq!( versioned.rules_for( "OurBot/2.1" ).len( ), 2 );
+}
+
+#[test]
+fn test_grouping_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+
+    let blank_lines = RobotsParser::from_stringable( ROBOTS_UNSPACED, host.clone( ) );
+    let rfc9309 = ParseOptions::default( ).grouping( GroupingMode::Rfc9309 );
+    let grouped = RobotsParser::parse_with( host.clone( ), ROBOTS_UNSPACED, &rfc9309 );
+
+    assert_eq!( blank_lines.groups( ).count( ), 1 );
+    assert_eq!( grouped.groups( ).count( ), 2 );
+    assert_eq!( grouped.grouping_mode( ), GroupingMode::Rfc9309 );
+
+    let recursed = blank_lines.get_all_anomalies( ).into_iter( ).filter(
+        | a |{ match a { Anomaly::RecursedUserAgent( _ ) => true, _ => false } }
+    ).count( );
+    assert_eq!( recursed, 1 );
+    assert!( grouped.get_all_anomalies( ).is_empty( ) );
+
+    let bot: Vec< &Rule > = grouped.groups( ).next( ).unwrap( ).rules( ).iter( )
+        .map( | rule |{ &rule.value } ).collect( );
+    assert_eq!( bot.len( ), 2 );
+    assert!( bot.contains( &&Rule::Disallow( String::from( "/also-bot-free" ) ) ) );
+
+    //NOTE: the Sitemaps after the last section are records of their own rather than part of the section
+    let wild = RobotsParser::parse_with( host, ROBOTS_WILD, &rfc9309 );
+    assert_eq!( wild.get_sitemaps( ).len( ), 2 );
+    assert!( wild.get_all_anomalies( ).iter( ).all( | a |{
+        match a { Anomaly::MissSectionedDirective( _, _ ) => false, _ => true }
+    } ) );
+}
+
+#[test]
+fn test_bytes_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+
+    let mut bytes = b"\xEF\xBB\xBF".to_vec( );
+    bytes.extend_from_slice( ROBOTS_WILD.as_bytes( ) );
+    bytes.extend_from_slice( b"# caf\xE9 au lait \xF0\x9F\n" );
+
+    let clean = RobotsParser::from_stringable( ROBOTS_WILD, host.clone( ) );
+    let decoded = RobotsParser::parse_bytes( host, &bytes );
+
+    let names = | robots: &RobotsParser |{
+        robots.groups( ).map( | group |{ group.names( ).clone( ) } ).collect::< Vec< Vec< String > > >( )
+    };
+    assert_eq!( names( &clean ), names( &decoded ) );
+    let rules = | robots: &RobotsParser |{
+        robots.rules_for( "bender" ).into_iter( )
+            .map( | rule |{ rule.value.clone( ) } ).collect::< Vec< Rule > >( )
+    };
+    assert_eq!( rules( &clean ), rules( &decoded ) );
+
+    let invalid: Vec< &Spanned< Anomaly > > = decoded.get_all_anomalies_spanned( ).into_iter( ).filter(
+        | a |{ match a.value { Anomaly::InvalidEncoding( _ ) => true, _ => false } }
+    ).collect( );
+    assert_eq!( invalid.len( ), 2 );
+    assert_eq!( invalid[ 0 ].value, Anomaly::InvalidEncoding( String::from( "E9" ) ) );
+    assert_eq!( invalid[ 1 ].value, Anomaly::InvalidEncoding( String::from( "F0 9F" ) ) );
+    assert_eq!( invalid[ 0 ].span.line, ROBOTS_WILD.matches( '\n' ).count( ) + 1 );
+
+    let bom = decoded.get_toplevel_anomalies_spanned( ).iter( ).find( | a |{
+        match a.value { Anomaly::ByteOrderMark( _ ) => true, _ => false }
+    } ).unwrap( );
+    assert_eq!( bom.value, Anomaly::ByteOrderMark( String::from( "EF BB BF" ) ) );
+    assert_eq!( bom.span, Span{ line: 1, start: 0, end: 3 } );
+    assert!( clean.get_all_anomalies( ).iter( ).all(
+        | a |{ match a { Anomaly::ByteOrderMark( _ ) => false, _ => true } }
+    ) );
+}
+
+#[test]
+fn test_limit_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+
+    let admin = BaseUrl::try_from( "https://example.web/admin/" ).ok( ).unwrap( );
+    let cut = ROBOTS_WILD.find( "Disallow:/admin/" ).unwrap( );
+
+    let options = ParseOptions::default( );
+
+    //NOTE: the limit falls part way through the Disallow line, which is dropped entirely
+    let truncated = RobotsParser::parse_with( host.clone( ), ROBOTS_WILD, &options.max_bytes( cut + 4 ) );
+    assert!( truncated.is_allowed( &admin, "bot" ) );
+    assert!( truncated.get_sitemaps( ).is_empty( ) );
+
+    let anomaly = truncated.get_toplevel_anomalies_spanned( ).last( ).unwrap( );
+    assert_eq!( anomaly.value, Anomaly::Truncated( cut ) );
+    assert_eq!( anomaly.span.line, 2 );
+
+    let bytes = RobotsParser::parse_bytes_with( host.clone( ), ROBOTS_WILD.as_bytes( ),
+                                                &options.max_bytes( cut + 4 ) );
+    assert_eq!( bytes.get_toplevel_anomalies( ).last( ), Some( &&Anomaly::Truncated( cut ) ) );
+
+    //NOTE: a line which fits is kept even when its line ending falls just past the limit
+    let ending = RobotsParser::parse_with( host.clone( ), ROBOTS_WILD, &options.max_bytes( cut - 1 ) );
+    assert_eq!( ending.get_toplevel_anomalies( ).last( ), Some( &&Anomaly::Truncated( cut ) ) );
+
+    for limit in &[ ROBOTS_WILD.len( ), ROBOTS_WILD.len( ) - 1 ] {
+        let whole = RobotsParser::parse_with( host.clone( ), ROBOTS_WILD, &options.max_bytes( *limit ) );
+        assert!( !whole.is_allowed( &admin, "bot" ) );
+        assert!( whole.get_all_anomalies( ).iter( ).all(
+            | a |{ match a { Anomaly::Truncated( _ ) => false, _ => true } }
+        ) );
+    }
+    assert_eq!( DEFAULT_MAX_BYTES, 512000 );
+}
+
+#[test]
+fn test_misspelt_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+
+    let misspelt = RobotsParser::from_stringable( ROBOTS_MISSPELT, host.clone( ) );
+
+    let private = BaseUrl::try_from( "https://example.web/private" ).ok( ).unwrap( );
+    let drafts = BaseUrl::try_from( "https://example.web/drafts" ).ok( ).unwrap( );
+    let bot_free = BaseUrl::try_from( "https://example.web/bot-free" ).ok( ).unwrap( );
+
+    assert!( !misspelt.is_allowed( &private, "somebot" ) );
+    assert!( !misspelt.is_allowed( &drafts, "somebot" ) );
+    assert!( !misspelt.is_allowed( &bot_free, "Bot" ) );
+    assert!( misspelt.is_allowed( &bot_free, "somebot" ) );
+    assert_eq!( misspelt.get_sitemaps( ).len( ), 1 );
+
+    let fixed: Vec< &Spanned< Anomaly > > = misspelt.get_all_anomalies_spanned( ).into_iter( ).filter(
+        | a |{ match a.value { Anomaly::Misspelling( _, _ ) => true, _ => false } }
+    ).collect( );
+    assert_eq!( fixed.len( ), 6 );
+    assert!( fixed.iter( ).any( | a |{
+        a.value == Anomaly::Misspelling( String::from( "Disalow /drafts" ),
+                                         String::from( "Disallow: /drafts" ) )
+    } ) );
+
+    //NOTE: a bare Disallow isn't fixed up in to an empty rule, it's left as a line which can't be read
+    let bare = RobotsParser::parse_with( host, "User-agent: *\nDisallow\n", &ParseOptions::lenient( ) );
+    assert!( bare.is_allowed( &private, "somebot" ) );
+    assert!( bare.rules_for( "somebot" ).is_empty( ) );
+    assert_eq!( bare.get_agent_anomalies( "somebot" ),
+                vec![ &Anomaly::UnknownFormat( String::from( "Disallow" ) ) ] );
+}
+
+#[test]
+fn test_commented_fix_ups( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+    let private = BaseUrl::try_from( "https://example.web/private" ).ok( ).unwrap( );
+    let drafts = BaseUrl::try_from( "https://example.web/drafts" ).ok( ).unwrap( );
+
+    //NOTE: a comment leaves the parser waiting for context when the next line needs fixing up
+    let commented = RobotsParser::from_stringable( "# fixed up\n\
+                                                    user-Agent: *\n\
+                                                    # also fixed up\n\
+                                                    disalow: /private\n\
+                                                    # still fixed up\n\
+                                                    DISALLOW: /drafts\n", host );
+
+    assert!( !commented.is_allowed( &private, "bot" ) );
+    assert!( !commented.is_allowed( &drafts, "bot" ) );
+}
+
+#[test]
+fn test_options_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+
+    let private = BaseUrl::try_from( "https://example.web/private" ).ok( ).unwrap( );
+    let drafts = BaseUrl::try_from( "https://example.web/drafts" ).ok( ).unwrap( );
+
+    let lenient = RobotsParser::parse_with( host.clone( ), ROBOTS_MISSPELT, &ParseOptions::lenient( ) );
+    let strict = RobotsParser::parse_with( host.clone( ), ROBOTS_MISSPELT, &ParseOptions::verbatim( ) );
+
+    assert!( !lenient.is_allowed( &drafts, "somebot" ) );
+    assert!( strict.is_allowed( &private, "somebot" ) );
+    assert!( strict.groups( ).next( ).is_none( ) );
+    assert!( strict.get_all_anomalies( ).iter( ).all(
+        | a |{ match a { Anomaly::Misspelling( _, _ ) => false, _ => true } }
+    ) );
+
+    let quiet = RobotsParser::parse_with( host.clone( ), ROBOTS_WILD,
+                                          &ParseOptions::default( ).collect_comments( false ) );
+    assert!( quiet.get_all_anomalies( ).iter( ).all(
+        | a |{ match a { Anomaly::Comment( _, _ ) => false, _ => true } }
+    ) );
+
+    let plain = ParseOptions::default( ).extensions( false ).policy( MatchingPolicy::rfc9309( ) );
+    let delays = RobotsParser::parse_with( host, ROBOTS_DELAYS, &plain );
+    assert_eq!( delays.crawl_delay( "somebot" ), None );
+    assert_eq!( delays.matching_policy( ), MatchingPolicy::rfc9309( ) );
+    assert_eq!( delays.parse_options( ), plain );
+}
+
+#[test]
+fn test_strict_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+
+    assert!( RobotsParser::parse_strict( host.clone( ), ROBOTS_WILD ).is_ok( ) );
+
+    let misspelt = RobotsParser::parse_strict( host.clone( ), ROBOTS_MISSPELT ).err( ).unwrap( );
+    assert_eq!( misspelt.line, 1 );
+    assert_eq!( misspelt.kind, AnomalyKind::UnknownDirective );
+
+    let lenient = RobotsParser::parse_strict_with( host.clone( ), ROBOTS_MISSPELT,
+                                                   &ParseOptions::lenient( ) );
+    assert_eq!( lenient.err( ).unwrap( ).kind, AnomalyKind::Misspelling );
+
+    let delays = RobotsParser::parse_strict( host.clone( ), ROBOTS_DELAYS ).err( ).unwrap( );
+    assert_eq!( delays.line, 9 );
+    assert_eq!( delays.kind, AnomalyKind::BadArgument );
+    assert_eq!( delays.anomaly.kind( ), delays.kind );
+
+    //NOTE: a line no directive can be read from is still a problem when it follows a comment
+    let commented = RobotsParser::parse_strict( host.clone( ), "User-agent: bot\n\
+                                                               # why not\n\
+                                                               nonsense\n\
+                                                               Disallow: /x\n" ).err( ).unwrap( );
+    assert_eq!( commented.line, 3 );
+    assert_eq!( commented.kind, AnomalyKind::UnknownFormat );
+
+    let top = RobotsParser::parse_strict( host.clone( ), "# hi\nnonsense\n" ).err( ).unwrap( );
+    assert_eq!( top.line, 2 );
+    assert_eq!( top.anomaly.value, Anomaly::UnknownFormat( String::from( "nonsense" ) ) );
+
+    //NOTE: the problem is found in the section the line closes, before the next section opens
+    let grouped = ParseOptions::lenient( ).grouping( GroupingMode::Rfc9309 );
+    let closing = RobotsParser::parse_strict_with( host, "User-agent: a\n\
+                                                          Disallow: /x\n\
+                                                          user-agent: b\n\
+                                                          nonsense\n", &grouped ).err( ).unwrap( );
+    assert_eq!( closing.line, 3 );
+    assert_eq!( closing.kind, AnomalyKind::Casing );
+}
+
+#[test]
+fn test_status_robots( ) {
+
+    let host = BaseUrl::try_from( "https://example.web" ).ok( ).unwrap( );
+    let admin = BaseUrl::try_from( "https://example.web/admin/" ).ok( ).unwrap( );
+    let page = BaseUrl::try_from( "https://example.web/page" ).ok( ).unwrap( );
+
+    let found = RobotsParser::from_status_and_body( host.clone( ), 200, ROBOTS_WILD.as_bytes( ) );
+    assert!( !found.is_allowed( &admin, "bot" ) );
+    assert!( found.is_allowed( &page, "bot" ) );
+
+    for status in &[ 301, 404, 410 ] {
+        let missing = RobotsParser::from_status_and_body( host.clone( ), *status, b"Disallow: /" );
+        assert!( missing.is_allowed( &admin, "bot" ) );
+        assert!( missing.is_allowed( &page, "bot" ) );
+    }
+
+    for status in &[ 500, 503, 0 ] {
+        let failing = RobotsParser::from_status_and_body( host.clone( ), *status, ROBOTS_WILD.as_bytes( ) );
+        assert!( !failing.is_allowed( &page, "bot" ) );
+        assert!( !failing.is_allowed( &page, "Mozilla/5.0 (compatible; OurBot/2.1)" ) );
+    }
+
+    let rfc = RobotsParser::unreachable( host.clone( ) );
+    assert_eq!( rfc.check( &page, "bot" ), Ok( Verdict::Disallowed ) );
+
+    //NOTE: documents which were never read still answer questions the way they were asked to
+    let options = ParseOptions::default( ).policy( MatchingPolicy::rfc9309( ) );
+    for status in &[ 404, 503 ] {
+        let built = RobotsParser::from_status_and_body_with( host.clone( ), *status, b"", &options );
+        assert_eq!( built.parse_options( ), options );
+        assert_eq!( built.matching_policy( ), MatchingPolicy::rfc9309( ) );
+    }
+    assert_eq!( RobotsParser::allow_all_with( host.clone( ), &options ).parse_options( ), options );
+    assert_eq!( RobotsParser::disallow_all_with( host.clone( ), &options ).parse_options( ), options );
+    assert_eq!( RobotsParser::unreachable_with( host, &options ).parse_options( ), options );
+}